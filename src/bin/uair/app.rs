@@ -1,14 +1,27 @@
 use crate::config::{Config, ConfigBuilder};
-use crate::session::{Overridables, Session, SessionId};
+use crate::goal::GoalProgress;
+use crate::history::{self, Tracker};
+use crate::session::{
+	Env, Finish, Hook, Overridables, Process, Program, Progress, Session, SessionId,
+};
+use crate::snapshot::Snapshot;
 use crate::socket::{Listener, Stream};
 use crate::timer::{State, UairTimer};
-use crate::{Args, Error};
+use crate::{catch_term_signals, Args, Error};
+use async_signal::Signals;
 use futures_lite::FutureExt;
-use log::error;
+use log::{error, warn};
+use std::env;
 use std::fs;
 use std::io::{self, Error as IoError, ErrorKind, Write};
+use std::mem;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
-use uair::{Command, FetchArgs, JumpArgs, ListenArgs, PauseArgs, ResumeArgs};
+use uair::{
+	get_history_path, get_state_path, AddTimeArgs, Command, FetchArgs, JumpArgs, ListenArgs,
+	Markup, Notification, NotificationKind, Outcome, PauseArgs, Reply, RestartArgs, ResumeArgs,
+	Status, SubtractTimeArgs, TimerState,
+};
 
 pub struct App {
 	data: AppData,
@@ -17,69 +30,135 @@ pub struct App {
 
 impl App {
 	pub fn new(args: Args) -> Result<Self, Error> {
-		let timer = UairTimer::new(Duration::from_secs(1), args.quiet);
-		let data = AppData::new(args)?;
+		let color = !args.no_color && !matches!(env::var_os("NO_COLOR"), Some(v) if !v.is_empty());
+		let timer = UairTimer::new(
+			Duration::from_secs(1),
+			args.quiet,
+			args.markup,
+			args.json,
+			color,
+		);
+		let data = AppData::new(args, color)?;
 		Ok(App { data, timer })
 	}
 
-	pub async fn run(mut self) -> Result<(), Error> {
+	pub async fn run(mut self, mut signals: Signals) -> Result<(), Error> {
 		let mut stdout = io::stdout();
 		write!(stdout, "{}", self.data.config.startup_text)?;
 		stdout.flush()?;
 
-		loop {
-			match match self.timer.state {
-				State::PreInit => self.start_up().await,
-				State::Paused(duration) => self.pause_session(duration).await,
-				State::Resumed(start, dest) => self.run_session(start, dest).await,
-				State::Finished => break,
-			} {
+		while !matches!(self.timer.state, State::Finished) {
+			let now = Instant::now();
+			let prev = self.data.snapshot(&self.timer.state, now);
+			let res = async {
+				match self.timer.state {
+					State::PreInit => self.start_up().await,
+					State::Paused(duration) => self.pause_session(duration).await,
+					State::Resumed(start, dest) => self.run_session(start, dest).await,
+					State::Finished => Ok(()),
+				}
+				.map(|_| false)
+			}
+			.or(async { catch_term_signals(&mut signals).await.map(|_| true) })
+			.await;
+			match res {
+				Ok(true) => {
+					if !self.data.config.persist_state {
+						self.record(Outcome::Interrupted);
+					}
+					break;
+				}
 				Err(Error::ConfError(err)) => error!("{}", err),
 				Err(Error::DeserError(err)) => error!("{}", err),
+				Err(Error::ProtocolError(err)) => error!("{}", err),
+				Err(Error::JsonError(err)) => error!("{}", err),
 				Err(err) => return Err(err),
 				_ => {}
 			}
+			if self.data.snapshot(&self.timer.state, now) != prev {
+				self.data.save_state(&self.timer.state);
+			}
 		}
+		self.data.save_state(&self.timer.state);
 		Ok(())
 	}
 
 	async fn start_up(&mut self) -> Result<(), Error> {
+		if let Some(state) = self.data.restored.take() {
+			if matches!(state, State::Resumed(..)) {
+				self.data.tracker.resume();
+			}
+			self.timer.state = state;
+			self.reset_warnings();
+			return Ok(());
+		}
+
 		if !self.data.config.pause_at_start {
-			self.timer.state = self.data.initial_state();
+			let state = self.data.initial_state();
+			self.switch_session(state);
 			return Ok(());
 		}
 
 		match self.data.handle_commands::<false>().await? {
-			Event::Finished | Event::Command(Command::Resume(_) | Command::Next(_)) => {
-				self.timer.state = self.data.initial_state();
+			Event::Command(Command::Finish(_) | Command::Resume(_) | Command::Next(_)) => {
+				let state = self.data.initial_state();
+				self.switch_session(state);
 			}
 			Event::Command(Command::Prev(_)) => {}
+			Event::Restart(keep_state) => {
+				self.notify(NotificationKind::Restarted);
+				if !keep_state {
+					let state = self.data.initial_state();
+					self.switch_session(state);
+				}
+			}
+			Event::AddTime(delta) => {
+				self.timer.state = State::Paused(self.data.curr_session().duration + delta)
+			}
+			Event::SubtractTime(delta) => {
+				match self.data.curr_session().duration.checked_sub(delta) {
+					Some(duration) if !duration.is_zero() => {
+						self.timer.state = State::Paused(duration)
+					}
+					_ => self.finish_session(Finish::Manual).await,
+				}
+			}
 			Event::Jump(idx) => {
-				self.timer.state = self.data.initial_jump(idx);
+				let state = self.data.initial_jump(idx);
+				self.notify(NotificationKind::Jumped);
+				self.switch_session(state);
+			}
+			Event::Reload(stream) => {
+				self.data.handle_reload(stream).await?;
+				self.reset_warnings();
+				self.notify(NotificationKind::Reloaded);
 			}
-			Event::Command(Command::Reload(_)) => self.data.read_conf::<true>()?,
-			Event::Fetch(format, stream) => {
+			Event::Fetch(overrides, markup, stream) => {
 				self.data
-					.handle_fetch_paused(
-						Some(&Overridables::new().format(&format)),
-						stream,
-						Duration::ZERO,
-					)
+					.handle_fetch_paused(Some(&*overrides), markup, false, stream, Duration::ZERO)
 					.await?
 			}
-			Event::Listen(overrid, stream) => self
-				.timer
-				.writer
-				.add_stream(stream.into_blocking(), overrid),
-			Event::ListenExit(overrid, stream) => {
+			Event::Listen(args, stream) => {
+				self.timer.writer.add_stream(stream.into_blocking(), args)
+			}
+			Event::ListenExit(args, stream) => {
 				self.data
 					.handle_fetch_paused(
-						overrid.and_then(|o| self.data.curr_session().overrides.get(&o)),
+						args.overrid
+							.and_then(|o| self.data.curr_session().overrides.get(&o)),
+						args.markup,
+						args.json,
 						stream,
 						Duration::ZERO,
 					)
 					.await?
 			}
+			Event::Status(stream) => {
+				self.data
+					.handle_status::<false>(stream, self.data.curr_session().duration)
+					.await?
+			}
+			Event::Subscribe(stream) => self.timer.writer.add_subscriber(stream.into_blocking()),
 			_ => unreachable!(),
 		}
 
@@ -89,44 +168,83 @@ impl App {
 	async fn run_session(&mut self, start: Instant, dest: Instant) -> Result<(), Error> {
 		match self
 			.timer
-			.start(self.data.curr_session(), start, dest)
+			.start(self.data.curr_session(), self.data.env(None), start, dest)
 			.or(self.data.handle_commands::<true>())
 			.await?
 		{
-			Event::Finished => {
-				let res = self.data.curr_session().run_command();
-				self.timer.state = if self.data.sid.is_last() {
-					State::Finished
-				} else {
-					self.data.next_session()
-				};
-				res?;
-			}
+			Event::Finished => self.finish_session(Finish::Natural).await,
+			Event::Command(Command::Finish(_)) => self.finish_session(Finish::Manual).await,
 			Event::Command(Command::Pause(_)) => {
-				self.timer.state = State::Paused(dest - Instant::now())
+				self.timer.state = State::Paused(dest - Instant::now());
+				self.data.tracker.pause();
+				self.notify(NotificationKind::Paused);
+			}
+			Event::AddTime(delta) => {
+				self.timer.state = State::Resumed(Instant::now(), dest + delta)
+			}
+			Event::Restart(keep_state) => {
+				self.record(Outcome::Interrupted);
+				self.notify(NotificationKind::Restarted);
+				let state = self.data.restart_session::<true>(keep_state);
+				self.switch_session(state);
+			}
+			Event::SubtractTime(delta) => {
+				let now = Instant::now();
+				match dest.checked_sub(delta) {
+					Some(dest) if dest > now => self.timer.state = State::Resumed(now, dest),
+					_ => self.finish_session(Finish::Manual).await,
+				}
+			}
+			Event::Command(Command::Next(_)) => {
+				self.notify(NotificationKind::Skipped);
+				self.record(Outcome::Skipped);
+				let state = self.data.next_session();
+				self.switch_session(state);
 			}
-			Event::Command(Command::Next(_)) => self.timer.state = self.data.next_session(),
-			Event::Command(Command::Prev(_)) => self.timer.state = self.data.prev_session(),
-			Event::Jump(idx) => self.timer.state = self.data.jump_session(idx),
-			Event::Command(Command::Reload(_)) => self.data.read_conf::<true>()?,
-			Event::Fetch(format, stream) => {
+			Event::Command(Command::Prev(_)) => {
+				self.notify(NotificationKind::Skipped);
+				self.record(Outcome::Skipped);
+				let state = self.data.prev_session();
+				self.switch_session(state);
+			}
+			Event::Jump(idx) => {
+				self.run_hook(Hook::Skip);
+				self.record(Outcome::Skipped);
+				let state = self.data.jump_session(idx);
+				self.notify(NotificationKind::Jumped);
+				self.switch_session(state);
+			}
+			Event::Reload(stream) => {
+				self.data.handle_reload(stream).await?;
+				self.reset_warnings();
+				self.notify(NotificationKind::Reloaded);
+			}
+			Event::Fetch(overrides, markup, stream) => {
 				self.data
-					.handle_fetch_resumed(Some(&Overridables::new().format(&format)), stream, dest)
+					.handle_fetch_resumed(Some(&*overrides), markup, false, stream, dest)
 					.await?
 			}
-			Event::Listen(overrid, stream) => self
-				.timer
-				.writer
-				.add_stream(stream.into_blocking(), overrid),
-			Event::ListenExit(overrid, stream) => {
+			Event::Listen(args, stream) => {
+				self.timer.writer.add_stream(stream.into_blocking(), args)
+			}
+			Event::ListenExit(args, stream) => {
 				self.data
 					.handle_fetch_resumed(
-						overrid.and_then(|o| self.data.curr_session().overrides.get(&o)),
+						args.overrid
+							.and_then(|o| self.data.curr_session().overrides.get(&o)),
+						args.markup,
+						args.json,
 						stream,
 						dest,
 					)
 					.await?
 			}
+			Event::Status(stream) => {
+				self.data
+					.handle_status::<true>(stream, dest - Instant::now())
+					.await?
+			}
+			Event::Subscribe(stream) => self.timer.writer.add_subscriber(stream.into_blocking()),
 			_ => unreachable!(),
 		}
 		Ok(())
@@ -135,66 +253,195 @@ impl App {
 	async fn pause_session(&mut self, duration: Duration) -> Result<(), Error> {
 		const DELTA: Duration = Duration::from_nanos(1_000_000_000 - 1);
 
-		self.timer
-			.writer
-			.write::<false>(self.data.curr_session(), duration + DELTA)?;
+		self.timer.writer.write::<false>(
+			self.data.curr_session(),
+			self.data.progress(),
+			duration + DELTA,
+		)?;
 
 		match self.data.handle_commands::<false>().await? {
-			Event::Finished => {
-				let res = self.data.curr_session().run_command();
-				self.timer.state = if self.data.sid.is_last() {
-					State::Finished
-				} else {
-					self.data.next_session()
-				};
-				res?;
-			}
+			Event::Command(Command::Finish(_)) => self.finish_session(Finish::Manual).await,
 			Event::Command(Command::Resume(_)) => {
 				let start = Instant::now();
 				self.timer.state = State::Resumed(start, start + duration);
-				self.timer
-					.writer
-					.write::<true>(self.data.curr_session(), duration + DELTA)?;
-			}
-			Event::Command(Command::Next(_)) => self.timer.state = self.data.next_session(),
-			Event::Command(Command::Prev(_)) => self.timer.state = self.data.prev_session(),
-			Event::Jump(idx) => self.timer.state = self.data.jump_session(idx),
-			Event::Command(Command::Reload(_)) => self.data.read_conf::<true>()?,
-			Event::Fetch(format, stream) => {
+				self.data.tracker.resume();
+				self.notify(if self.data.started {
+					NotificationKind::Resumed
+				} else {
+					NotificationKind::Started
+				});
+				self.data.started = true;
+				self.timer.writer.write::<true>(
+					self.data.curr_session(),
+					self.data.progress(),
+					duration + DELTA,
+				)?;
+			}
+			Event::AddTime(delta) => self.timer.state = State::Paused(duration + delta),
+			Event::Restart(keep_state) => {
+				self.record(Outcome::Interrupted);
+				self.notify(NotificationKind::Restarted);
+				let state = self.data.restart_session::<false>(keep_state);
+				self.switch_session(state);
+			}
+			Event::SubtractTime(delta) => match duration.checked_sub(delta) {
+				Some(duration) if !duration.is_zero() => self.timer.state = State::Paused(duration),
+				_ => self.finish_session(Finish::Manual).await,
+			},
+			Event::Command(Command::Next(_)) => {
+				self.notify(NotificationKind::Skipped);
+				self.record(Outcome::Skipped);
+				let state = self.data.next_session();
+				self.switch_session(state);
+			}
+			Event::Command(Command::Prev(_)) => {
+				self.notify(NotificationKind::Skipped);
+				self.record(Outcome::Skipped);
+				let state = self.data.prev_session();
+				self.switch_session(state);
+			}
+			Event::Jump(idx) => {
+				self.run_hook(Hook::Skip);
+				self.record(Outcome::Skipped);
+				let state = self.data.jump_session(idx);
+				self.notify(NotificationKind::Jumped);
+				self.switch_session(state);
+			}
+			Event::Reload(stream) => {
+				self.data.handle_reload(stream).await?;
+				self.reset_warnings();
+				self.notify(NotificationKind::Reloaded);
+			}
+			Event::Fetch(overrides, markup, stream) => {
 				self.data
-					.handle_fetch_paused(
-						Some(&Overridables::new().format(&format)),
-						stream,
-						duration + DELTA,
-					)
+					.handle_fetch_paused(Some(&*overrides), markup, false, stream, duration + DELTA)
 					.await?
 			}
-			Event::Listen(overrid, stream) => self
-				.timer
-				.writer
-				.add_stream(stream.into_blocking(), overrid),
-			Event::ListenExit(overrid, stream) => {
+			Event::Listen(args, stream) => {
+				self.timer.writer.add_stream(stream.into_blocking(), args)
+			}
+			Event::ListenExit(args, stream) => {
 				self.data
 					.handle_fetch_paused(
-						overrid.and_then(|o| self.data.curr_session().overrides.get(&o)),
+						args.overrid
+							.and_then(|o| self.data.curr_session().overrides.get(&o)),
+						args.markup,
+						args.json,
 						stream,
 						duration + DELTA,
 					)
 					.await?
 			}
+			Event::Status(stream) => {
+				self.data
+					.handle_status::<false>(stream, duration + DELTA)
+					.await?
+			}
+			Event::Subscribe(stream) => self.timer.writer.add_subscriber(stream.into_blocking()),
 			_ => unreachable!(),
 		}
 		Ok(())
 	}
+
+	async fn finish_session(&mut self, finish: Finish) {
+		self.data
+			.curr_session()
+			.run_command(&self.data.env(Some(finish)))
+			.await;
+		self.record(Outcome::Completed);
+		self.notify(NotificationKind::Finished);
+		if self.data.sid.is_last() {
+			self.timer.state = State::Finished;
+			self.notify(NotificationKind::TimerFinished);
+		} else {
+			let state = self.data.next_session();
+			self.switch_session(state);
+		}
+	}
+
+	fn switch_session(&mut self, state: State) {
+		self.data.started = matches!(state, State::Resumed(..));
+		self.timer.state = state;
+		self.reset_warnings();
+		if self.data.started {
+			self.data.tracker.resume();
+			self.notify(NotificationKind::Started);
+		}
+	}
+
+	fn reset_warnings(&mut self) {
+		let remaining = match self.timer.state {
+			State::Paused(duration) => duration,
+			State::Resumed(start, dest) => dest - start,
+			_ => return,
+		};
+		self.timer
+			.reset_warnings(self.data.curr_session(), remaining);
+	}
+
+	fn record(&mut self, outcome: Outcome) {
+		let tracker = mem::take(&mut self.data.tracker);
+		let Some(entry) = tracker.finish(self.data.curr_session(), outcome) else {
+			return;
+		};
+		if let Some(goal) = &self.data.config.goal {
+			self.data.goal.add(goal, &entry);
+		}
+		if !self.data.config.record_history {
+			return;
+		}
+		if let Err(err) = history::append(&self.data.history_path, &entry) {
+			error!("Could not record history: {}", err);
+		}
+	}
+
+	/// Notifies subscribers of a change in the state of the timer and runs the hook for it.
+	fn notify(&mut self, kind: NotificationKind) {
+		match kind {
+			NotificationKind::Started => self.run_hook(Hook::Start),
+			NotificationKind::Paused => self.run_hook(Hook::Pause),
+			NotificationKind::Resumed => self.run_hook(Hook::Resume),
+			NotificationKind::Skipped => self.run_hook(Hook::Skip),
+			NotificationKind::TimerFinished => {
+				Process {
+					program: Program::Shell(self.data.config.on_timer_finish.clone()),
+					envs: vec![("UAIR_CONFIG", self.data.config_path.clone())],
+					timeout: None,
+				}
+				.spawn();
+			}
+			_ => {}
+		}
+		let session = self.data.curr_session();
+		self.timer.writer.notify(Notification {
+			kind,
+			id: session.id.clone(),
+			name: session.name.clone(),
+			index: self.data.sid.curr(),
+			iteration: self.data.sid.iter_no,
+		});
+	}
+
+	fn run_hook(&self, hook: Hook) {
+		self.data
+			.curr_session()
+			.run_hook(hook, &self.data.env(None));
+	}
 }
 
 pub enum Event {
 	Command(Command),
 	Jump(usize),
-	Fetch(String, Stream),
+	Reload(Stream),
+	Fetch(Box<Overridables>, Option<Markup>, Stream),
 	Finished,
-	Listen(Option<String>, Stream),
-	ListenExit(Option<String>, Stream),
+	Listen(ListenArgs, Stream),
+	ListenExit(ListenArgs, Stream),
+	Status(Stream),
+	Subscribe(Stream),
+	AddTime(Duration),
+	SubtractTime(Duration),
+	Restart(bool),
 }
 
 struct AppData {
@@ -202,20 +449,123 @@ struct AppData {
 	sid: SessionId,
 	config: Config,
 	config_path: String,
+	state_path: PathBuf,
+	history_path: PathBuf,
+	started: bool,
+	markup: Markup,
+	color: bool,
+	restored: Option<State>,
+	tracker: Tracker,
+	goal: GoalProgress,
 }
 
 impl AppData {
-	fn new(args: Args) -> Result<Self, Error> {
+	fn new(args: Args, color: bool) -> Result<Self, Error> {
 		let mut data = AppData {
 			listener: Listener::new(&args.socket)?,
 			sid: SessionId::default(),
 			config: Config::default(),
 			config_path: args.config,
+			state_path: get_state_path().into(),
+			history_path: get_history_path().into(),
+			started: false,
+			markup: args.markup,
+			color,
+			restored: None,
+			tracker: Tracker::default(),
+			goal: GoalProgress::default(),
 		};
 		data.read_conf::<false>()?;
+		if data.config.persist_state {
+			data.restore_state();
+		}
+		if data.config.record_history {
+			data.restore_goal();
+		}
 		Ok(data)
 	}
 
+	fn restore_goal(&mut self) {
+		let Some(goal) = &self.config.goal else {
+			return;
+		};
+		match history::load(&self.history_path) {
+			Ok(entries) => {
+				for entry in &entries {
+					self.goal.add(goal, entry);
+				}
+			}
+			Err(err) => error!("Could not load history: {}", err),
+		}
+	}
+
+	fn progress(&self) -> Progress<'_> {
+		let next = self.sid.next();
+		Progress {
+			index: self.sid.curr(),
+			count: self.config.schedule.len(),
+			iteration: self.sid.iter_no,
+			iterations: self.config.iterations,
+			next: (!self.sid.is_last())
+				.then(|| &self.config.sessions[self.config.schedule[next.curr()]]),
+			goal: self.config.goal.as_ref().map(|goal| self.goal.status(goal)),
+			elapsed: self.tracker.focused(),
+		}
+	}
+
+	fn env(&self, finish: Option<Finish>) -> Env<'_> {
+		Env {
+			progress: self.progress(),
+			finish,
+			config: &self.config_path,
+		}
+	}
+
+	fn restore_state(&mut self) {
+		let snapshot = match Snapshot::load(&self.state_path) {
+			Ok(Some(snapshot)) => snapshot,
+			Ok(None) => return,
+			Err(err) => {
+				error!("Could not load saved state: {}", err);
+				return;
+			}
+		};
+		match snapshot.restore(&self.config, self.sid) {
+			Some((sid, started, state, tracker)) => {
+				self.sid = sid;
+				self.started = started;
+				self.restored = Some(state);
+				self.tracker = tracker;
+			}
+			None => warn!("Saved state does not match the config and will be ignored."),
+		}
+	}
+
+	fn snapshot(&self, state: &State, now: Instant) -> Option<Snapshot> {
+		Snapshot::new(
+			&self.config,
+			&self.sid,
+			self.started,
+			&self.tracker,
+			state,
+			now,
+		)
+	}
+
+	fn save_state(&self, state: &State) {
+		if !self.config.persist_state {
+			return;
+		}
+		let res = match self.snapshot(state, Instant::now()) {
+			Some(snapshot) => snapshot.save(&self.state_path),
+			None if matches!(state, State::Finished) => Snapshot::remove(&self.state_path),
+			None => Ok(()),
+		};
+		if let Err(err) = res {
+			error!("Could not save state: {}", err);
+		}
+	}
+
 	fn read_conf<const R: bool>(&mut self) -> Result<(), Error> {
 		let conf_data = fs::read_to_string(&self.config_path).map_err(|_| {
 			Error::IoError(IoError::new(
@@ -224,12 +574,16 @@ impl AppData {
 			))
 		})?;
 		let config = ConfigBuilder::deserialize(&conf_data)?.build()?;
-		let mut sid = SessionId::new(&config.sessions, config.iterations);
+		let mut sid = SessionId::new(&config.schedule, config.iterations);
 
 		if R {
 			let curr_id = &self.curr_session().id;
-			if let Some(&idx) = config.idmap.get(curr_id) {
-				sid = sid.jump(idx);
+			if let Some(pos) = config
+				.idmap
+				.get(curr_id)
+				.and_then(|&idx| config.position(idx, self.sid.curr()))
+			{
+				sid = sid.jump(pos);
 			}
 			if self.sid.iter_no < sid.total_iter {
 				sid.iter_no = self.sid.iter_no;
@@ -245,63 +599,142 @@ impl AppData {
 		let mut buffer = Vec::new();
 		loop {
 			let mut stream = self.listener.listen().await?;
-			buffer.clear();
-			let msg = stream.read(&mut buffer).await?;
-			let command: Command = bincode::deserialize(msg)?;
-			match command {
+			let command = match read_command(&mut stream, &mut buffer).await {
+				Ok(command) => command,
+				Err(err) => {
+					_ = stream.reply(&Reply::Error(err.to_string())).await;
+					return Err(err);
+				}
+			};
+			let res = match command {
 				Command::Pause(_) | Command::Toggle(_) if R => {
-					return Ok(Event::Command(Command::Pause(PauseArgs {})))
+					Ok(Event::Command(Command::Pause(PauseArgs {})))
 				}
+				Command::Pause(_) => Err("timer is already paused".into()),
 				Command::Resume(_) | Command::Toggle(_) if !R => {
-					return Ok(Event::Command(Command::Resume(ResumeArgs {})))
+					Ok(Event::Command(Command::Resume(ResumeArgs {})))
 				}
-				Command::Next(_) if !self.sid.is_last() => return Ok(Event::Command(command)),
-				Command::Prev(_) if !self.sid.is_first() => return Ok(Event::Command(command)),
-				Command::Finish(_) => return Ok(Event::Finished),
-				Command::Jump(JumpArgs { id }) => {
-					if let Some(idx) = self.config.idmap.get(&id) {
-						return Ok(Event::Jump(*idx));
+				Command::Resume(_) | Command::Toggle(_) => Err("timer is already resumed".into()),
+				Command::Next(_) if self.sid.is_last() => Err("no next session".into()),
+				Command::Prev(_) if self.sid.is_first() => Err("no previous session".into()),
+				Command::Next(_) | Command::Prev(_) => Ok(Event::Command(command)),
+				Command::Finish(_) => Ok(Event::Command(command)),
+				Command::Jump(JumpArgs { id }) => match self
+					.config
+					.idmap
+					.get(&id)
+					.and_then(|&idx| self.config.position(idx, self.sid.curr()))
+				{
+					Some(pos) => Ok(Event::Jump(pos)),
+					None => Err(format!("no session with id '{}'", id)),
+				},
+				Command::Reload(_) => return Ok(Event::Reload(stream)),
+				Command::Fetch(FetchArgs { format, markup }) => {
+					match Overridables::new().format(&format, self.config.strict_format) {
+						Ok(overrides) => {
+							return Ok(Event::Fetch(Box::new(overrides), markup, stream))
+						}
+						Err(err) => Err(format!("invalid format: {}", err)),
 					}
 				}
-				Command::Reload(_) => return Ok(Event::Command(command)),
-				Command::Fetch(FetchArgs { format }) => return Ok(Event::Fetch(format, stream)),
-				Command::Listen(ListenArgs { overrid, exit }) => {
-					return if exit {
-						Ok(Event::ListenExit(overrid, stream))
+				Command::Listen(args) => {
+					return if args.exit {
+						Ok(Event::ListenExit(args, stream))
 					} else {
-						Ok(Event::Listen(overrid, stream))
+						Ok(Event::Listen(args, stream))
 					}
 				}
-				_ => {}
+				Command::Status(_) => return Ok(Event::Status(stream)),
+				Command::Subscribe(_) => return Ok(Event::Subscribe(stream)),
+				Command::AddTime(AddTimeArgs { duration }) => Ok(Event::AddTime(duration)),
+				Command::SubtractTime(SubtractTimeArgs { duration }) => {
+					Ok(Event::SubtractTime(duration))
+				}
+				Command::Restart(RestartArgs { keep_state }) => Ok(Event::Restart(keep_state)),
+			};
+			match res {
+				Ok(event) => {
+					_ = stream.reply(&Reply::Ok).await;
+					return Ok(event);
+				}
+				Err(msg) => _ = stream.reply(&Reply::Error(msg)).await,
 			}
 		}
 	}
 
+	async fn handle_reload(&mut self, mut stream: Stream) -> Result<(), Error> {
+		if let Err(err) = self.read_conf::<true>() {
+			_ = stream.reply(&Reply::Error(err.to_string())).await;
+			return Err(err);
+		}
+		_ = stream.reply(&Reply::Ok).await;
+		Ok(())
+	}
+
 	async fn handle_fetch_resumed(
 		&self,
 		overrides: Option<&Overridables>,
+		markup: Option<Markup>,
+		json: bool,
 		mut stream: Stream,
 		dest: Instant,
 	) -> Result<(), Error> {
 		let remaining = dest - Instant::now();
-		let displayed = self.curr_session().display::<true>(remaining, overrides);
-		stream.write(format!("{}", displayed).as_bytes()).await?;
+		let displayed = self
+			.curr_session()
+			.display::<true>(remaining, self.progress(), overrides)
+			.markup(markup.unwrap_or(self.markup))
+			.color(self.color)
+			.json(json);
+		_ = stream.reply(&Reply::Output(displayed.to_string())).await;
 		Ok(())
 	}
 
 	async fn handle_fetch_paused(
 		&self,
 		overrides: Option<&Overridables>,
+		markup: Option<Markup>,
+		json: bool,
 		mut stream: Stream,
 		duration: Duration,
 	) -> Result<(), Error> {
-		let displayed = self.curr_session().display::<false>(duration, overrides);
-		stream.write(format!("{}", displayed).as_bytes()).await?;
+		let displayed = self
+			.curr_session()
+			.display::<false>(duration, self.progress(), overrides)
+			.markup(markup.unwrap_or(self.markup))
+			.color(self.color)
+			.json(json);
+		_ = stream.reply(&Reply::Output(displayed.to_string())).await;
+		Ok(())
+	}
+
+	async fn handle_status<const R: bool>(
+		&self,
+		mut stream: Stream,
+		remaining: Duration,
+	) -> Result<(), Error> {
+		let session = self.curr_session();
+		let status = Status {
+			id: session.id.clone(),
+			name: session.name.clone(),
+			index: self.sid.curr(),
+			iteration: self.sid.iter_no,
+			total_iterations: self.config.iterations,
+			state: if R {
+				TimerState::Resumed
+			} else {
+				TimerState::Paused
+			},
+			remaining: remaining.as_secs(),
+			total: session.duration.as_secs(),
+			autostart: session.autostart,
+		};
+		_ = stream.reply(&Reply::Status(status)).await;
 		Ok(())
 	}
 
 	fn initial_state(&self) -> State {
-		if self.config.iterations != Some(0) && !self.config.sessions.is_empty() {
+		if self.config.iterations != Some(0) && !self.config.schedule.is_empty() {
 			self.new_state()
 		} else {
 			State::Finished
@@ -317,7 +750,7 @@ impl AppData {
 	}
 
 	fn curr_session(&self) -> &Session {
-		&self.config.sessions[self.sid.curr()]
+		&self.config.sessions[self.config.schedule[self.sid.curr()]]
 	}
 
 	fn next_session(&mut self) -> State {
@@ -335,6 +768,18 @@ impl AppData {
 		self.new_state()
 	}
 
+	fn restart_session<const R: bool>(&self, keep_state: bool) -> State {
+		let duration = self.curr_session().duration;
+		if !keep_state {
+			self.new_state()
+		} else if R {
+			let start = Instant::now();
+			State::Resumed(start, start + duration)
+		} else {
+			State::Paused(duration)
+		}
+	}
+
 	fn new_state(&self) -> State {
 		let session = self.curr_session();
 		if session.autostart {
@@ -346,9 +791,19 @@ impl AppData {
 	}
 }
 
+async fn read_command(stream: &mut Stream, buffer: &mut Vec<u8>) -> Result<Command, Error> {
+	let msg = stream.read(buffer).await.map_err(Error::ProtocolError)?;
+	if stream.is_json() {
+		Ok(Command::from_json(msg)?)
+	} else {
+		Ok(bincode::deserialize(msg)?)
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use crate::{app::App, Args};
+	use uair::Markup;
 
 	#[test]
 	fn indicate_missing_config_file() {
@@ -357,6 +812,9 @@ mod tests {
 			socket: "/tmp/uair.sock".into(),
 			log: "-".into(),
 			quiet: false,
+			markup: Markup::Ansi,
+			json: false,
+			no_color: false,
 			version: false,
 		});
 		assert_eq!(
@@ -1,7 +1,13 @@
-use crate::session::{Color, Overridables, Session, TimeFormatToken, Token};
+use crate::goal::{Goal, Target};
+use crate::session::{
+	Bar, Color, Condition, Hooks, Op, Overridables, Paint, Session, SessionCommand,
+	TimeFormatToken, Token, Warning,
+};
+use chrono::format::{Item, StrftimeItems};
 use log::warn;
 use serde::de::Error as _;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::time::Duration;
@@ -11,11 +17,30 @@ use toml::de::Error;
 pub struct Config {
 	pub iterations: Option<u64>,
 	pub pause_at_start: bool,
+	pub persist_state: bool,
+	pub record_history: bool,
+	pub strict_format: bool,
+	pub on_timer_finish: String,
 	pub startup_text: String,
+	pub goal: Option<Goal>,
 	pub sessions: Vec<Session>,
+	pub schedule: Vec<usize>,
 	pub idmap: HashMap<String, usize>,
 }
 
+impl Config {
+	/// Returns the position in the schedule of the first occurrence of the session with index
+	/// `idx` at or after position `from`, wrapping around to the start of the schedule.
+	pub fn position(&self, idx: usize, from: usize) -> Option<usize> {
+		let from = from.min(self.schedule.len());
+		self.schedule[from..]
+			.iter()
+			.position(|&i| i == idx)
+			.map(|pos| from + pos)
+			.or_else(|| self.schedule.iter().position(|&i| i == idx))
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ConfigBuilder {
 	#[serde(default)]
@@ -24,9 +49,18 @@ pub struct ConfigBuilder {
 	#[serde(default)]
 	pause_at_start: bool,
 	#[serde(default)]
+	persist_state: bool,
+	#[serde(default)]
+	record_history: bool,
+	#[serde(default)]
+	strict_format: bool,
+	#[serde(default)]
+	on_timer_finish: String,
+	#[serde(default)]
 	startup_text: String,
 	#[serde(default)]
 	defaults: Defaults,
+	goal: Option<GoalBuilder>,
 	sessions: Vec<SessionBuilder>,
 }
 
@@ -41,17 +75,13 @@ impl ConfigBuilder {
 	pub fn build(self) -> Result<Config, Error> {
 		let mut idmap = HashMap::new();
 		let mut sessions = Vec::new();
-		for (idx, session) in self.sessions.into_iter().enumerate() {
-			let session = session.build(&self.defaults, idx);
-			if let Some(idx2) = idmap.get(&session.id) {
-				return Err(Error::custom(format!(
-					"Duplicate identifier {} present at index {} and {}.",
-					session.id, idx, idx2
-				)));
-			}
-			idmap.insert(session.id.clone(), idx);
-			sessions.push(session);
-		}
+		let schedule = expand(
+			self.sessions,
+			&self.defaults,
+			&mut Formats::new(self.strict_format),
+			&mut sessions,
+			&mut idmap,
+		)?;
 		Ok(Config {
 			iterations: if self.loop_on_end && self.iterations != Some(0) {
 				None
@@ -61,13 +91,131 @@ impl ConfigBuilder {
 				Some(1)
 			},
 			pause_at_start: self.pause_at_start,
+			persist_state: self.persist_state,
+			record_history: self.record_history,
+			strict_format: self.strict_format,
+			on_timer_finish: self.on_timer_finish,
 			startup_text: self.startup_text,
+			goal: self.goal.map(GoalBuilder::build).transpose()?,
 			sessions,
+			schedule,
 			idmap,
 		})
 	}
 }
 
+fn expand(
+	builders: Vec<SessionBuilder>,
+	defaults: &Defaults,
+	formats: &mut Formats,
+	sessions: &mut Vec<Session>,
+	idmap: &mut HashMap<String, usize>,
+) -> Result<Vec<usize>, Error> {
+	let mut schedule = Vec::new();
+	for mut builder in builders {
+		let repeat = builder.repeat.take().unwrap_or(1);
+		let part = if let Some(members) = builder.sessions.take() {
+			if builder.has_properties() {
+				return Err(Error::custom(format!(
+					"Session group at index {} cannot have session properties.",
+					sessions.len()
+				)));
+			}
+			expand(members, defaults, formats, sessions, idmap)?
+		} else {
+			let idx = sessions.len();
+			let session = builder.build(defaults, idx, formats)?;
+			if let Some(idx2) = idmap.get(&session.id) {
+				return Err(Error::custom(format!(
+					"Duplicate identifier {} present at index {} and {}.",
+					session.id, idx, idx2
+				)));
+			}
+			idmap.insert(session.id.clone(), idx);
+			sessions.push(session);
+			vec![idx]
+		};
+		for _ in 0..repeat {
+			schedule.extend_from_slice(&part);
+		}
+	}
+	Ok(schedule)
+}
+
+/// Command of a session, given either as a command line run through the shell or as an array of
+/// a program and its arguments.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+enum CommandBuilder {
+	Shell(String),
+	Argv(Vec<String>),
+}
+
+impl CommandBuilder {
+	fn build(self, formats: &mut Formats) -> Result<SessionCommand, Error> {
+		Ok(match self {
+			CommandBuilder::Shell(command) => SessionCommand::Shell(command),
+			CommandBuilder::Argv(argv) => SessionCommand::Argv(
+				argv.iter()
+					.map(|arg| formats.parse(arg))
+					.collect::<Result<_, _>>()?,
+			),
+		})
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct WarningBuilder {
+	#[serde(with = "humantime_serde")]
+	at: Duration,
+	#[serde(default)]
+	command: String,
+	#[serde(default)]
+	text: String,
+}
+
+impl WarningBuilder {
+	fn build(self) -> Warning {
+		Warning {
+			at: self.at,
+			command: self.command,
+			text: self.text,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct GoalBuilder {
+	#[serde(default)]
+	ids: Vec<String>,
+	count: Option<u64>,
+	#[serde(with = "humantime_serde")]
+	#[serde(default)]
+	duration: Option<Duration>,
+	#[serde(with = "humantime_serde")]
+	#[serde(default)]
+	day_start: Duration,
+}
+
+impl GoalBuilder {
+	fn build(self) -> Result<Goal, Error> {
+		let target = match (self.count, self.duration) {
+			(Some(count), None) => Target::Count(count),
+			(None, Some(duration)) => Target::Duration(duration),
+			_ => {
+				return Err(Error::custom(
+					"Goal must specify exactly one of count and duration.",
+				))
+			}
+		};
+		Ok(Goal {
+			ids: self.ids,
+			target,
+			day_start: self.day_start,
+		})
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Defaults {
 	#[serde(default = "Defaults::name")]
@@ -76,11 +224,38 @@ pub struct Defaults {
 	#[serde(default = "Defaults::duration")]
 	duration: Duration,
 	#[serde(default = "Defaults::command")]
-	command: String,
+	command: CommandBuilder,
+	#[serde(with = "humantime_serde")]
+	#[serde(default)]
+	command_timeout: Option<Duration>,
+	#[serde(default)]
+	command_blocking: bool,
 	#[serde(default = "Defaults::format")]
 	format: String,
 	#[serde(default = "Defaults::time_format")]
 	time_format: String,
+	#[serde(default = "Defaults::clock_format")]
+	clock_format: String,
+	#[serde(default = "Defaults::tooltip_format")]
+	tooltip_format: String,
+	#[serde(default = "Defaults::bar_width")]
+	bar_width: usize,
+	#[serde(default = "Defaults::bar_fill")]
+	bar_fill: String,
+	#[serde(default = "Defaults::bar_empty")]
+	bar_empty: String,
+	#[serde(default)]
+	bar_partial: String,
+	#[serde(default)]
+	on_start: String,
+	#[serde(default)]
+	on_pause: String,
+	#[serde(default)]
+	on_resume: String,
+	#[serde(default)]
+	on_skip: String,
+	#[serde(default)]
+	warnings: Vec<WarningBuilder>,
 	#[serde(default = "Defaults::autostart")]
 	autostart: bool,
 	#[serde(default = "Defaults::paused_state_text")]
@@ -98,8 +273,8 @@ impl Defaults {
 	fn duration() -> Duration {
 		Duration::from_secs(25 * 60)
 	}
-	fn command() -> String {
-		"notify-send 'Session Completed!'".into()
+	fn command() -> CommandBuilder {
+		CommandBuilder::Shell("notify-send 'Session Completed!'".into())
 	}
 	fn format() -> String {
 		"{time}\n".into()
@@ -107,6 +282,21 @@ impl Defaults {
 	fn time_format() -> String {
 		"%*-Yyear%P %*-Bmonth%P %*-Dday%P %*-Hh %*-Mm %*-Ss".into()
 	}
+	fn clock_format() -> String {
+		"%H:%M".into()
+	}
+	fn tooltip_format() -> String {
+		"{name}".into()
+	}
+	fn bar_width() -> usize {
+		10
+	}
+	fn bar_fill() -> String {
+		"#".into()
+	}
+	fn bar_empty() -> String {
+		"-".into()
+	}
 	fn autostart() -> bool {
 		false
 	}
@@ -127,8 +317,21 @@ impl Default for Defaults {
 			name: Defaults::name(),
 			duration: Defaults::duration(),
 			command: Defaults::command(),
+			command_timeout: None,
+			command_blocking: false,
 			format: Defaults::format(),
 			time_format: Defaults::time_format(),
+			clock_format: Defaults::clock_format(),
+			tooltip_format: Defaults::tooltip_format(),
+			bar_width: Defaults::bar_width(),
+			bar_fill: Defaults::bar_fill(),
+			bar_empty: Defaults::bar_empty(),
+			bar_partial: String::new(),
+			on_start: String::new(),
+			on_pause: String::new(),
+			on_resume: String::new(),
+			on_skip: String::new(),
+			warnings: Vec::new(),
 			autostart: Defaults::autostart(),
 			paused_state_text: Defaults::paused_state_text(),
 			resumed_state_text: Defaults::resumed_state_text(),
@@ -144,39 +347,121 @@ struct SessionBuilder {
 	#[serde(with = "humantime_serde")]
 	#[serde(default)]
 	duration: Option<Duration>,
-	command: Option<String>,
+	command: Option<CommandBuilder>,
+	#[serde(with = "humantime_serde")]
+	#[serde(default)]
+	command_timeout: Option<Duration>,
+	command_blocking: Option<bool>,
 	format: Option<String>,
 	time_format: Option<String>,
+	clock_format: Option<String>,
+	tooltip_format: Option<String>,
+	bar_width: Option<usize>,
+	bar_fill: Option<String>,
+	bar_empty: Option<String>,
+	bar_partial: Option<String>,
+	on_start: Option<String>,
+	on_pause: Option<String>,
+	on_resume: Option<String>,
+	on_skip: Option<String>,
+	warnings: Option<Vec<WarningBuilder>>,
 	autostart: Option<bool>,
 	paused_state_text: Option<String>,
 	resumed_state_text: Option<String>,
 	#[serde(default)]
 	overrides: HashMap<String, OverridablesBuilder>,
+	repeat: Option<u64>,
+	sessions: Option<Vec<SessionBuilder>>,
 }
 
 impl SessionBuilder {
-	fn build(self, defaults: &Defaults, idx: usize) -> Session {
+	fn has_properties(&self) -> bool {
+		self.id.is_some()
+			|| self.name.is_some()
+			|| self.duration.is_some()
+			|| self.command.is_some()
+			|| self.command_timeout.is_some()
+			|| self.command_blocking.is_some()
+			|| self.format.is_some()
+			|| self.time_format.is_some()
+			|| self.clock_format.is_some()
+			|| self.tooltip_format.is_some()
+			|| self.bar_width.is_some()
+			|| self.bar_fill.is_some()
+			|| self.bar_empty.is_some()
+			|| self.bar_partial.is_some()
+			|| self.on_start.is_some()
+			|| self.on_pause.is_some()
+			|| self.on_resume.is_some()
+			|| self.on_skip.is_some()
+			|| self.warnings.is_some()
+			|| self.autostart.is_some()
+			|| self.paused_state_text.is_some()
+			|| self.resumed_state_text.is_some()
+			|| !self.overrides.is_empty()
+	}
+
+	fn build(
+		self,
+		defaults: &Defaults,
+		idx: usize,
+		formats: &mut Formats,
+	) -> Result<Session, Error> {
 		let mut default_overrides = defaults.overrides.clone();
 		default_overrides.extend(self.overrides);
 		let overrides = default_overrides
 			.into_iter()
 			.map(|(k, v)| {
 				let default = defaults.overrides.get(&k);
-				(k, v.build(default))
+				Ok((k, v.build(default, formats)?))
 			})
-			.collect();
-		Session {
+			.collect::<Result<_, Error>>()?;
+		Ok(Session {
 			id: self.id.unwrap_or_else(|| idx.to_string()),
 			name: self.name.unwrap_or_else(|| defaults.name.clone()),
 			duration: self.duration.unwrap_or(defaults.duration),
-			command: self.command.unwrap_or_else(|| defaults.command.clone()),
-			format: self
-				.format
-				.map(|f| Token::parse(&f))
-				.unwrap_or_else(|| Token::parse(&defaults.format)),
-			time_format: TimeFormatToken::parse(
-				self.time_format.as_ref().unwrap_or(&defaults.time_format),
-			),
+			command: self
+				.command
+				.unwrap_or_else(|| defaults.command.clone())
+				.build(formats)?,
+			command_timeout: self.command_timeout.or(defaults.command_timeout),
+			command_blocking: self.command_blocking.unwrap_or(defaults.command_blocking),
+			format: formats.parse(self.format.as_ref().unwrap_or(&defaults.format))?,
+			time_format: formats
+				.parse_time(self.time_format.as_ref().unwrap_or(&defaults.time_format))?,
+			clock_format: check_clock_format(
+				self.clock_format
+					.unwrap_or_else(|| defaults.clock_format.clone()),
+			)?,
+			tooltip_format: formats.parse(
+				self.tooltip_format
+					.as_ref()
+					.unwrap_or(&defaults.tooltip_format),
+			)?,
+			bar: Bar {
+				width: self.bar_width.unwrap_or(defaults.bar_width),
+				fill: self.bar_fill.unwrap_or_else(|| defaults.bar_fill.clone()),
+				empty: self.bar_empty.unwrap_or_else(|| defaults.bar_empty.clone()),
+				partial: self
+					.bar_partial
+					.unwrap_or_else(|| defaults.bar_partial.clone()),
+			},
+			hooks: Hooks {
+				start: self.on_start.unwrap_or_else(|| defaults.on_start.clone()),
+				pause: self.on_pause.unwrap_or_else(|| defaults.on_pause.clone()),
+				resume: self.on_resume.unwrap_or_else(|| defaults.on_resume.clone()),
+				skip: self.on_skip.unwrap_or_else(|| defaults.on_skip.clone()),
+			},
+			warnings: {
+				let mut warnings: Vec<_> = self
+					.warnings
+					.unwrap_or_else(|| defaults.warnings.clone())
+					.into_iter()
+					.map(WarningBuilder::build)
+					.collect();
+				warnings.sort_by_key(|w| Reverse(w.at));
+				warnings
+			},
 			autostart: self.autostart.unwrap_or(defaults.autostart),
 			paused_state_text: self
 				.paused_state_text
@@ -185,7 +470,7 @@ impl SessionBuilder {
 				.resumed_state_text
 				.unwrap_or_else(|| defaults.resumed_state_text.clone()),
 			overrides,
-		}
+		})
 	}
 }
 
@@ -198,7 +483,19 @@ impl FromStr for Token {
 			"{percent}" => Ok(Token::Percent),
 			"{time}" => Ok(Token::Time),
 			"{total}" => Ok(Token::Total),
+			"{bar}" => Ok(Token::Bar),
 			"{state}" => Ok(Token::State),
+			"{elapsed}" => Ok(Token::Elapsed),
+			"{percent_done}" => Ok(Token::PercentDone),
+			"{eta}" => Ok(Token::Eta),
+			"{index}" => Ok(Token::Index),
+			"{count}" => Ok(Token::Count),
+			"{iter}" => Ok(Token::Iter),
+			"{iters}" => Ok(Token::Iters),
+			"{next_name}" => Ok(Token::NextName),
+			"{warning}" => Ok(Token::Warning),
+			"{goal_done}" => Ok(Token::GoalDone),
+			"{goal_total}" => Ok(Token::GoalTotal),
 			"{black}" => Ok(Token::Color(Color::Black)),
 			"{red}" => Ok(Token::Color(Color::Red)),
 			"{green}" => Ok(Token::Color(Color::Green)),
@@ -207,8 +504,102 @@ impl FromStr for Token {
 			"{purple}" => Ok(Token::Color(Color::Purple)),
 			"{cyan}" => Ok(Token::Color(Color::Cyan)),
 			"{white}" => Ok(Token::Color(Color::White)),
-			"{end}" => Ok(Token::Color(Color::End)),
-			_ => Err(()),
+			"{bright_black}" => Ok(Token::Color(Color::BrightBlack)),
+			"{bright_red}" => Ok(Token::Color(Color::BrightRed)),
+			"{bright_green}" => Ok(Token::Color(Color::BrightGreen)),
+			"{bright_yellow}" => Ok(Token::Color(Color::BrightYellow)),
+			"{bright_blue}" => Ok(Token::Color(Color::BrightBlue)),
+			"{bright_purple}" => Ok(Token::Color(Color::BrightPurple)),
+			"{bright_cyan}" => Ok(Token::Color(Color::BrightCyan)),
+			"{bright_white}" => Ok(Token::Color(Color::BrightWhite)),
+			"{bold}" => Ok(Token::Color(Color::Bold)),
+			"{italic}" => Ok(Token::Color(Color::Italic)),
+			"{underline}" => Ok(Token::Color(Color::Underline)),
+			"{end}" | "{reset}" => Ok(Token::Color(Color::End)),
+			_ => {
+				let spec = s.strip_prefix('{').and_then(|s| s.strip_suffix('}'));
+				if let Some(paint) = spec.and_then(|s| s.strip_prefix("fg:")) {
+					Ok(Token::Color(Color::Fg(paint.parse()?)))
+				} else if let Some(paint) = spec.and_then(|s| s.strip_prefix("bg:")) {
+					Ok(Token::Color(Color::Bg(paint.parse()?)))
+				} else {
+					Err(())
+				}
+			}
+		}
+	}
+}
+
+impl FromStr for Paint {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some(hex) = s.strip_prefix('#') {
+			if hex.len() != 6 || !hex.is_ascii() {
+				return Err(());
+			}
+			let channel = |i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| ());
+			return Ok(Paint::Rgb(channel(0)?, channel(2)?, channel(4)?));
+		}
+		if let Ok(n) = s.parse() {
+			return Ok(Paint::Indexed(n));
+		}
+		let (bright, name) = match s.strip_prefix("bright_") {
+			Some(name) => (8, name),
+			None => (0, s),
+		};
+		let n = match name {
+			"black" => 0,
+			"red" => 1,
+			"green" => 2,
+			"yellow" => 3,
+			"blue" => 4,
+			"purple" => 5,
+			"cyan" => 6,
+			"white" => 7,
+			_ => return Err(()),
+		};
+		Ok(Paint::Indexed(bright + n))
+	}
+}
+
+impl FromStr for Condition {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some(condition) = s.strip_prefix('!') {
+			return Ok(Condition::Not(Box::new(condition.parse()?)));
+		}
+		match s {
+			"paused" => return Ok(Condition::Paused),
+			"resumed" => return Ok(Condition::Resumed),
+			"warning" => return Ok(Condition::Warning),
+			_ => {}
+		}
+
+		let Some(i) = s.find(['<', '>', '=', '!']) else {
+			return Err(format!("unknown condition '{}'", s));
+		};
+		let (op, len) = match &s[i..] {
+			r if r.starts_with("<=") => (Op::Le, 2),
+			r if r.starts_with(">=") => (Op::Ge, 2),
+			r if r.starts_with("!=") => (Op::Ne, 2),
+			r if r.starts_with('<') => (Op::Lt, 1),
+			r if r.starts_with('>') => (Op::Gt, 1),
+			r if r.starts_with('=') => (Op::Eq, 1),
+			_ => return Err(format!("unknown operator in condition '{}'", s)),
+		};
+		let (key, value) = (s[..i].trim(), s[i + len..].trim());
+		let duration = || {
+			humantime::parse_duration(value)
+				.map_err(|err| format!("invalid duration '{}' in condition: {}", value, err))
+		};
+		match key {
+			"id" if matches!(op, Op::Eq | Op::Ne) => Ok(Condition::Id(op, value.into())),
+			"id" => Err("session ids can only be compared using '=' and '!='".into()),
+			"remaining" => Ok(Condition::Remaining(op, duration()?)),
+			"elapsed" => Ok(Condition::Elapsed(op, duration()?)),
+			_ => Err(format!("unknown condition '{}'", s)),
 		}
 	}
 }
@@ -217,33 +608,136 @@ impl FromStr for Token {
 struct OverridablesBuilder {
 	format: Option<String>,
 	time_format: Option<String>,
+	clock_format: Option<String>,
+	tooltip_format: Option<String>,
+	bar_width: Option<usize>,
+	bar_fill: Option<String>,
+	bar_empty: Option<String>,
+	bar_partial: Option<String>,
 	paused_state_text: Option<String>,
 	resumed_state_text: Option<String>,
 }
 
 impl OverridablesBuilder {
-	fn build(self, defaults: Option<&OverridablesBuilder>) -> Overridables {
+	fn build(
+		self,
+		defaults: Option<&OverridablesBuilder>,
+		formats: &mut Formats,
+	) -> Result<Overridables, Error> {
 		let default_ob = OverridablesBuilder::default();
 		let defaults = defaults.unwrap_or(&default_ob);
-		Overridables {
+		Ok(Overridables {
 			format: self
 				.format
 				.or(defaults.format.clone())
-				.map(|f| Token::parse(&f)),
+				.map(|f| formats.parse(&f))
+				.transpose()?,
 			time_format: self
 				.time_format
 				.or(defaults.time_format.clone())
-				.map(|f| TimeFormatToken::parse(&f)),
+				.map(|f| formats.parse_time(&f))
+				.transpose()?,
+			clock_format: self
+				.clock_format
+				.or(defaults.clock_format.clone())
+				.map(check_clock_format)
+				.transpose()?,
+			tooltip_format: self
+				.tooltip_format
+				.or(defaults.tooltip_format.clone())
+				.map(|f| formats.parse(&f))
+				.transpose()?,
+			bar_width: self.bar_width.or(defaults.bar_width),
+			bar_fill: self.bar_fill.or(defaults.bar_fill.clone()),
+			bar_empty: self.bar_empty.or(defaults.bar_empty.clone()),
+			bar_partial: self.bar_partial.or(defaults.bar_partial.clone()),
 			paused_state_text: self
 				.paused_state_text
 				.or(defaults.paused_state_text.clone()),
 			resumed_state_text: self
 				.resumed_state_text
 				.or(defaults.resumed_state_text.clone()),
+		})
+	}
+}
+
+/// Parses the formats of sessions, caching the tokens of every distinct format so that formats
+/// shared by sessions, such as those of `[defaults]`, are parsed and warned about only once.
+struct Formats {
+	strict: bool,
+	formats: HashMap<String, Vec<Token>>,
+	time_formats: HashMap<String, Vec<TimeFormatToken>>,
+}
+
+impl Formats {
+	fn new(strict: bool) -> Self {
+		Formats {
+			strict,
+			formats: HashMap::new(),
+			time_formats: HashMap::new(),
+		}
+	}
+
+	fn parse(&mut self, format: &str) -> Result<Vec<Token>, Error> {
+		if let Some(tokens) = self.formats.get(format) {
+			return Ok(tokens.clone());
 		}
+		let tokens = parse_format(format, self.strict)?;
+		self.formats.insert(format.into(), tokens.clone());
+		Ok(tokens)
+	}
+
+	fn parse_time(&mut self, format: &str) -> Result<Vec<TimeFormatToken>, Error> {
+		if let Some(tokens) = self.time_formats.get(format) {
+			return Ok(tokens.clone());
+		}
+		let tokens = parse_time_format(format, self.strict)?;
+		self.time_formats.insert(format.into(), tokens.clone());
+		Ok(tokens)
+	}
+}
+
+fn parse_format(format: &str, strict: bool) -> Result<Vec<Token>, Error> {
+	let mut unknown = None;
+	let tokens = Token::parse(format, |err| {
+		warn!("Format {:?} contains {}.", format, err);
+		unknown.get_or_insert(err);
+	})
+	.map_err(|err| Error::custom(format!("Invalid format {:?}: {}.", format, err)))?;
+	match unknown {
+		Some(err) if strict => Err(Error::custom(format!(
+			"Invalid format {:?}: {}.",
+			format, err
+		))),
+		_ => Ok(tokens),
 	}
 }
 
+fn parse_time_format(format: &str, strict: bool) -> Result<Vec<TimeFormatToken>, Error> {
+	let mut unknown = None;
+	let tokens = TimeFormatToken::parse(format, |err| {
+		warn!("Time format {:?} contains {}.", format, err);
+		unknown.get_or_insert(err);
+	});
+	match unknown {
+		Some(err) if strict => Err(Error::custom(format!(
+			"Invalid time format {:?}: {}.",
+			format, err
+		))),
+		_ => Ok(tokens),
+	}
+}
+
+fn check_clock_format(format: String) -> Result<String, Error> {
+	if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+		return Err(Error::custom(format!(
+			"Invalid clock format \"{}\".",
+			format
+		)));
+	}
+	Ok(format)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -274,4 +768,125 @@ time_format = "%M:%S"
 		});
 		Ok(())
 	}
+
+	#[test]
+	fn expand_session_groups() -> Result<(), Error> {
+		let config = ConfigBuilder::deserialize(
+			r#"
+[[sessions]]
+repeat = 2
+
+[[sessions.sessions]]
+id = "work"
+
+[[sessions.sessions]]
+id = "short"
+
+[[sessions]]
+id = "long"
+"#,
+		)?
+		.build()?;
+		let ids: Vec<_> = config.sessions.iter().map(|s| s.id.as_str()).collect();
+		assert_eq!(ids, ["work", "short", "long"]);
+		assert_eq!(config.schedule, [0, 1, 0, 1, 2]);
+		assert_eq!(config.position(0, 1), Some(2));
+		assert_eq!(config.position(1, 4), Some(1));
+		assert_eq!(config.position(2, 2), Some(4));
+		Ok(())
+	}
+
+	#[test]
+	fn parse_argv_command() -> Result<(), Error> {
+		let config = ConfigBuilder::deserialize(
+			r#"
+[[sessions]]
+id = "work"
+command = ["notify-send", "Work done", "{name}"]
+
+[[sessions]]
+id = "rest"
+"#,
+		)?
+		.build()?;
+		let SessionCommand::Argv(argv) = &config.sessions[0].command else {
+			panic!("command of work session is not an argument vector");
+		};
+		assert_eq!(
+			argv,
+			&[
+				vec![Token::Literal("notify-send".into())],
+				vec![Token::Literal("Work done".into())],
+				vec![Token::Name],
+			]
+		);
+		assert!(matches!(
+			&config.sessions[1].command,
+			SessionCommand::Shell(command) if command == "notify-send 'Session Completed!'"
+		));
+		Ok(())
+	}
+
+	#[test]
+	fn reject_session_group_with_properties() {
+		let res = ConfigBuilder::deserialize(
+			r#"
+[[sessions]]
+name = "Work"
+sessions = [{ id = "work" }]
+"#,
+		)
+		.and_then(ConfigBuilder::build);
+		assert!(res.is_err());
+	}
+
+	#[test]
+	fn reject_invalid_clock_format() {
+		let res = ConfigBuilder::deserialize(
+			r#"
+[defaults]
+clock_format = "%H:%"
+
+[[sessions]]
+"#,
+		)
+		.and_then(ConfigBuilder::build);
+		assert!(res.is_err());
+	}
+
+	#[test]
+	fn report_unknown_specifiers() -> Result<(), Error> {
+		let conf = r#"
+[defaults]
+format = "{name}: {tiem}\n"
+time_format = "%H:%L"
+
+[[sessions]]
+
+[[sessions]]
+"#;
+		testing_logger::setup();
+		ConfigBuilder::deserialize(conf)?.build()?;
+		testing_logger::validate(|captured_logs| {
+			let bodies: Vec<_> = captured_logs.iter().map(|log| log.body.as_str()).collect();
+			assert_eq!(
+				bodies,
+				[
+					"Format \"{name}: {tiem}\\n\" contains unknown format specifier '{tiem}' \
+					 at position 9.",
+					"Time format \"%H:%L\" contains unknown time format specifier '%L' at \
+					 position 4.",
+				]
+			);
+		});
+
+		let err = ConfigBuilder::deserialize(&format!("strict_format = true\n{}", conf))?
+			.build()
+			.err()
+			.unwrap();
+		assert!(err
+			.to_string()
+			.contains("unknown format specifier '{tiem}' at position 9"));
+		Ok(())
+	}
 }
@@ -0,0 +1,124 @@
+use chrono::{DateTime, Local, NaiveDate, TimeDelta, TimeZone};
+use std::time::Duration;
+use uair::{HistoryEntry, Outcome};
+
+pub struct Goal {
+	pub ids: Vec<String>,
+	pub target: Target,
+	pub day_start: Duration,
+}
+
+#[derive(Clone, Copy)]
+pub enum Target {
+	Count(u64),
+	Duration(Duration),
+}
+
+impl Goal {
+	/// Returns the day to which the given moment belongs, with days starting `day_start` after
+	/// midnight.
+	fn day<Tz: TimeZone>(&self, time: DateTime<Tz>) -> NaiveDate {
+		let time = time.with_timezone(&Local);
+		TimeDelta::from_std(self.day_start)
+			.ok()
+			.and_then(|delta| time.checked_sub_signed(delta))
+			.unwrap_or(time)
+			.date_naive()
+	}
+
+	fn counts(&self, id: &str) -> bool {
+		self.ids.is_empty() || self.ids.iter().any(|i| i == id)
+	}
+}
+
+/// Progress made towards the goal during the current day. Completed sessions count towards
+/// `Target::Count`, while the focused time of all sessions counts towards `Target::Duration`.
+#[derive(Default)]
+pub struct GoalProgress {
+	day: Option<NaiveDate>,
+	count: u64,
+	focused: Duration,
+}
+
+impl GoalProgress {
+	pub fn add(&mut self, goal: &Goal, entry: &HistoryEntry) {
+		if !goal.counts(&entry.id) {
+			return;
+		}
+		let day = goal.day(entry.end);
+		if self.day < Some(day) {
+			*self = GoalProgress {
+				day: Some(day),
+				..Default::default()
+			};
+		} else if self.day > Some(day) {
+			return;
+		}
+		if entry.outcome == Outcome::Completed {
+			self.count += 1;
+		}
+		self.focused += Duration::from_secs(entry.focused);
+	}
+
+	pub fn status(&self, goal: &Goal) -> GoalStatus {
+		let today = self.day == Some(goal.day(Local::now()));
+		match goal.target {
+			Target::Count(total) => GoalStatus::Count(if today { self.count } else { 0 }, total),
+			Target::Duration(total) => {
+				GoalStatus::Duration(if today { self.focused } else { Duration::ZERO }, total)
+			}
+		}
+	}
+}
+
+/// Progress towards the goal as displayed by `{goal_done}` and `{goal_total}`.
+#[derive(Clone, Copy)]
+pub enum GoalStatus {
+	Count(u64, u64),
+	Duration(Duration, Duration),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(id: &str, outcome: Outcome, end: DateTime<Local>) -> HistoryEntry {
+		HistoryEntry {
+			id: id.into(),
+			name: id.into(),
+			outcome,
+			planned: 1500,
+			focused: 1200,
+			pauses: 0,
+			start: end.fixed_offset(),
+			end: end.fixed_offset(),
+		}
+	}
+
+	#[test]
+	fn track_goal_progress() {
+		let goal = Goal {
+			ids: vec!["work".into()],
+			target: Target::Count(8),
+			day_start: Duration::ZERO,
+		};
+		let now = Local::now();
+		let yesterday = now - TimeDelta::days(1);
+		let mut progress = GoalProgress::default();
+		progress.add(&goal, &entry("work", Outcome::Completed, yesterday));
+		progress.add(&goal, &entry("work", Outcome::Completed, now));
+		progress.add(&goal, &entry("work", Outcome::Skipped, now));
+		progress.add(&goal, &entry("rest", Outcome::Completed, now));
+		progress.add(&goal, &entry("work", Outcome::Completed, yesterday));
+		assert!(matches!(progress.status(&goal), GoalStatus::Count(1, 8)));
+
+		let goal = Goal {
+			target: Target::Duration(Duration::from_secs(3600)),
+			..goal
+		};
+		assert!(matches!(
+			progress.status(&goal),
+			GoalStatus::Duration(d, _) if d == Duration::from_secs(2400)
+		));
+	}
+}
@@ -0,0 +1,168 @@
+use crate::session::Session;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use uair::{HistoryEntry, Outcome};
+
+/// Keeps track of what happens to the current session until it is recorded in the history.
+#[derive(Default)]
+pub struct Tracker {
+	start: Option<DateTime<Local>>,
+	resumed: Option<Instant>,
+	focused: Duration,
+	pauses: u32,
+}
+
+/// State of a tracker which is saved along with the state of the timer.
+#[derive(Serialize, Deserialize, PartialEq, Default)]
+pub struct SavedTracker {
+	start: Option<DateTime<Local>>,
+	#[serde(with = "humantime_serde")]
+	focused: Duration,
+	pauses: u32,
+}
+
+impl Tracker {
+	pub fn resume(&mut self) {
+		self.start.get_or_insert_with(Local::now);
+		self.resumed = Some(Instant::now());
+	}
+
+	pub fn pause(&mut self) {
+		if let Some(resumed) = self.resumed.take() {
+			self.focused += resumed.elapsed();
+			self.pauses += 1;
+		}
+	}
+
+	/// Returns the time for which the session has been resumed.
+	pub fn focused(&self) -> Duration {
+		self.focused_at(Instant::now())
+	}
+
+	fn focused_at(&self, now: Instant) -> Duration {
+		self.focused
+			+ self
+				.resumed
+				.map_or(Duration::ZERO, |r| now.saturating_duration_since(r))
+	}
+
+	pub fn save(&self, now: Instant) -> SavedTracker {
+		SavedTracker {
+			start: self.start,
+			focused: Duration::from_millis(self.focused_at(now).as_millis() as u64),
+			pauses: self.pauses,
+		}
+	}
+
+	/// Returns a paused tracker in the saved state.
+	pub fn restore(saved: &SavedTracker) -> Self {
+		Tracker {
+			start: saved.start,
+			resumed: None,
+			focused: saved.focused,
+			pauses: saved.pauses,
+		}
+	}
+
+	/// Returns the history entry of the session, or `None` if it was never resumed, in which case
+	/// nothing is recorded.
+	pub fn finish(self, session: &Session, outcome: Outcome) -> Option<HistoryEntry> {
+		let start = self.start?;
+		let end = Local::now();
+		let focused = self.focused();
+		Some(HistoryEntry {
+			id: session.id.clone(),
+			name: session.name.clone(),
+			outcome,
+			planned: session.duration.as_secs(),
+			focused: focused.as_secs(),
+			pauses: self.pauses,
+			start: start.fixed_offset(),
+			end: end.fixed_offset(),
+		})
+	}
+}
+
+/// Reads all valid entries of the history file, which is treated as empty if it does not exist.
+pub fn load(path: &Path) -> io::Result<Vec<HistoryEntry>> {
+	match fs::read_to_string(path) {
+		Ok(data) => Ok(data
+			.lines()
+			.filter_map(|line| serde_json::from_str(line).ok())
+			.collect()),
+		Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+		Err(err) => Err(err),
+	}
+}
+
+pub fn append(path: &Path, entry: &HistoryEntry) -> io::Result<()> {
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	let mut line = serde_json::to_vec(entry)?;
+	line.push(b'\n');
+	OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(path)?
+		.write_all(&line)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::ConfigBuilder;
+	use std::thread;
+
+	#[test]
+	fn track_session() {
+		let session = ConfigBuilder::deserialize("[[sessions]]\nid = \"work\"\nduration = \"25m\"")
+			.unwrap()
+			.build()
+			.unwrap()
+			.sessions
+			.remove(0);
+		let mut tracker = Tracker::default();
+		tracker.pause();
+		assert_eq!(tracker.focused(), Duration::ZERO);
+
+		tracker.resume();
+		thread::sleep(Duration::from_millis(20));
+		tracker.pause();
+		tracker.pause();
+		let focused = tracker.focused();
+		assert!(focused >= Duration::from_millis(20));
+		thread::sleep(Duration::from_millis(20));
+		assert_eq!(tracker.focused(), focused);
+
+		tracker.resume();
+		thread::sleep(Duration::from_millis(20));
+		assert!(tracker.focused() >= focused + Duration::from_millis(20));
+		tracker.pause();
+
+		let entry = tracker.finish(&session, Outcome::Skipped).unwrap();
+		assert_eq!(entry.id, "work");
+		assert!(matches!(entry.outcome, Outcome::Skipped));
+		assert_eq!(entry.planned, 1500);
+		assert_eq!(entry.focused, 0);
+		assert_eq!(entry.pauses, 2);
+		assert!(entry.start < entry.end);
+	}
+
+	#[test]
+	fn skip_unstarted_session() {
+		let session = ConfigBuilder::deserialize("[[sessions]]\nid = \"work\"")
+			.unwrap()
+			.build()
+			.unwrap()
+			.sessions
+			.remove(0);
+		let mut tracker = Tracker::default();
+		tracker.pause();
+		assert!(tracker.finish(&session, Outcome::Completed).is_none());
+	}
+}
@@ -1,13 +1,16 @@
 mod app;
 mod config;
+mod goal;
+mod history;
 mod session;
+mod snapshot;
 mod socket;
 mod timer;
 
 use crate::app::App;
 use argh::FromArgs;
 use async_signal::{Signal, Signals};
-use futures_lite::{FutureExt, StreamExt};
+use futures_lite::StreamExt;
 use log::{error, LevelFilter};
 use simplelog::{ColorChoice, Config as LogConfig, TermLogger, TerminalMode, WriteLogger};
 use std::env;
@@ -15,7 +18,7 @@ use std::fmt::Display;
 use std::fs::File;
 use std::io::{self, Write};
 use std::process::ExitCode;
-use uair::get_socket_path;
+use uair::{get_socket_path, Markup};
 
 fn main() -> ExitCode {
 	let args: Args = argh::from_env();
@@ -42,7 +45,14 @@ fn main() -> ExitCode {
 		}
 	};
 
-	if let Err(err) = async_io::block_on(app.run().or(catch_term_signals())) {
+	let signals = match Signals::new([Signal::Term, Signal::Int, Signal::Quit]) {
+		Ok(signals) => signals,
+		Err(err) => {
+			return raise_err(err, enable_stderr);
+		}
+	};
+
+	if let Err(err) = async_io::block_on(app.run(signals)) {
 		return raise_err(err, enable_stderr);
 	}
 
@@ -68,6 +78,19 @@ pub struct Args {
 	#[argh(switch, short = 'q')]
 	quiet: bool,
 
+	/// markup used for colors and styles on standard output: ansi (default), pango, polybar,
+	/// lemonbar, tmux or plain.
+	#[argh(option, short = 'm', default = "Markup::Ansi")]
+	markup: Markup,
+
+	/// output a JSON object for waybar custom modules on every line of standard output
+	#[argh(switch, short = 'j')]
+	json: bool,
+
+	/// output text without colors and styles. Also enabled by a non-empty NO_COLOR variable.
+	#[argh(switch)]
+	no_color: bool,
+
 	/// display version number and then exit.
 	#[argh(switch, short = 'v')]
 	version: bool,
@@ -83,8 +106,7 @@ fn get_config_path() -> String {
 	}
 }
 
-async fn catch_term_signals() -> Result<(), Error> {
-	let mut signals = Signals::new([Signal::Term, Signal::Int, Signal::Quit])?;
+pub async fn catch_term_signals(signals: &mut Signals) -> Result<(), Error> {
 	signals.next().await;
 	Ok(())
 }
@@ -125,4 +147,8 @@ pub enum Error {
 	ConfError(#[from] toml::de::Error),
 	#[error("Deserialization Error: {0}")]
 	DeserError(#[from] bincode::Error),
+	#[error("Protocol Error: {0}")]
+	ProtocolError(io::Error),
+	#[error("JSON Error: {0}")]
+	JsonError(#[from] serde_json::Error),
 }
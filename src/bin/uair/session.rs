@@ -1,11 +1,19 @@
-use async_process::Command;
+use crate::goal::GoalStatus;
+use async_io::Timer;
+use async_process::{Child, Command};
+use chrono::Local;
+use futures_lite::FutureExt;
 use humantime::format_duration;
+use log::{error, info, warn};
+use serde::Serialize;
 use std::collections::HashMap;
-use std::fmt::{self, Display, Formatter};
-use std::io;
+use std::fmt::{self, Display, Formatter, Write as _};
+use std::mem;
 use std::process::Stdio;
+use std::thread;
 use std::time::Duration;
-use winnow::combinator::{alt, opt, peek, preceded, repeat};
+use uair::Markup;
+use winnow::combinator::{alt, opt, preceded};
 use winnow::token::{any, one_of, rest, take_until};
 use winnow::{ModalResult, Parser};
 
@@ -13,9 +21,17 @@ pub struct Session {
 	pub id: String,
 	pub name: String,
 	pub duration: Duration,
-	pub command: String,
+	pub command: SessionCommand,
+	pub command_timeout: Option<Duration>,
+	pub command_blocking: bool,
 	pub format: Vec<Token>,
 	pub time_format: Vec<TimeFormatToken>,
+	pub clock_format: String,
+	pub tooltip_format: Vec<Token>,
+	pub bar: Bar,
+	pub hooks: Hooks,
+	/// Warnings ordered by decreasing threshold.
+	pub warnings: Vec<Warning>,
 	pub autostart: bool,
 	pub paused_state_text: String,
 	pub resumed_state_text: String,
@@ -26,10 +42,12 @@ impl Session {
 	pub fn display<'s, const R: bool>(
 		&'s self,
 		time: Duration,
+		progress: Progress<'s>,
 		overrid: Option<&'s Overridables>,
 	) -> DisplayableSession<'s, R> {
 		DisplayableSession {
 			session: self,
+			progress,
 			time: DisplayableTime {
 				time,
 				format: overrid
@@ -39,32 +57,308 @@ impl Session {
 			format: overrid
 				.and_then(|o| o.format.as_ref())
 				.unwrap_or(&self.format),
+			clock_format: overrid
+				.and_then(|o| o.clock_format.as_deref())
+				.unwrap_or(&self.clock_format),
+			tooltip_format: overrid
+				.and_then(|o| o.tooltip_format.as_ref())
+				.unwrap_or(&self.tooltip_format),
+			bar: DisplayableBar {
+				width: overrid.and_then(|o| o.bar_width).unwrap_or(self.bar.width),
+				fill: overrid
+					.and_then(|o| o.bar_fill.as_deref())
+					.unwrap_or(&self.bar.fill),
+				empty: overrid
+					.and_then(|o| o.bar_empty.as_deref())
+					.unwrap_or(&self.bar.empty),
+				partial: overrid
+					.and_then(|o| o.bar_partial.as_deref())
+					.unwrap_or(&self.bar.partial),
+			},
 			pst_override: overrid.and_then(|o| o.paused_state_text.as_deref()),
 			rst_override: overrid.and_then(|o| o.resumed_state_text.as_deref()),
+			markup: Markup::Ansi,
+			color: true,
+			json: false,
 		}
 	}
 
-	pub fn run_command(&self) -> io::Result<()> {
-		if !self.command.is_empty() {
-			let duration = humantime::format_duration(self.duration).to_string();
-			Command::new("/bin/sh")
-				.env("name", &self.name)
-				.env("duration", duration)
-				.arg("-c")
-				.arg(&self.command)
-				.stdin(Stdio::null())
-				.stdout(Stdio::null())
-				.stderr(Stdio::null())
-				.spawn()?;
+	/// Runs the command of the session, waiting for it to complete if `command_blocking` is set.
+	pub async fn run_command(&self, env: &Env<'_>) {
+		let program = match &self.command {
+			SessionCommand::Shell(command) => Program::Shell(command.clone()),
+			SessionCommand::Argv(argv) => {
+				let remaining = self.duration.saturating_sub(env.progress.elapsed);
+				let displayed = self
+					.display::<false>(remaining, env.progress, None)
+					.markup(Markup::Plain)
+					.color(false);
+				Program::Argv(
+					argv.iter()
+						.map(|arg| {
+							DisplayableSession {
+								format: arg,
+								..displayed
+							}
+							.to_string()
+						})
+						.collect(),
+				)
+			}
+		};
+		let process = self.process(program, env);
+		if self.command_blocking {
+			process.run().await
+		} else {
+			process.spawn()
+		}
+	}
+
+	pub fn run_hook(&self, hook: Hook, env: &Env) {
+		let command = match hook {
+			Hook::Start => &self.hooks.start,
+			Hook::Pause => &self.hooks.pause,
+			Hook::Resume => &self.hooks.resume,
+			Hook::Skip => &self.hooks.skip,
+		};
+		self.process(Program::Shell(command.clone()), env).spawn()
+	}
+
+	pub fn run_warning(&self, warning: &Warning, env: &Env) {
+		self.process(Program::Shell(warning.command.clone()), env)
+			.spawn()
+	}
+
+	/// Returns the warning with the lowest threshold which the remaining time has reached.
+	pub fn warning(&self, remaining: Duration) -> Option<&Warning> {
+		self.warnings.iter().rev().find(|w| remaining <= w.at)
+	}
+
+	fn process(&self, program: Program, env: &Env) -> Process {
+		// `name` and `duration` are kept for compatibility with commands written for older
+		// versions.
+		let mut vars = vec![
+			("name", self.name.clone()),
+			("duration", format_duration(self.duration).to_string()),
+			("UAIR_ID", self.id.clone()),
+			("UAIR_NAME", self.name.clone()),
+			("UAIR_DURATION", self.duration.as_secs().to_string()),
+			("UAIR_ELAPSED", env.progress.elapsed.as_secs().to_string()),
+			("UAIR_INDEX", env.progress.index.to_string()),
+			("UAIR_ITERATION", env.progress.iteration.to_string()),
+			("UAIR_CONFIG", env.config.into()),
+		];
+		if let Some(iterations) = env.progress.iterations {
+			vars.push(("UAIR_ITERATIONS", iterations.to_string()));
+		}
+		if let Some(next) = env.progress.next {
+			vars.push(("UAIR_NEXT_ID", next.id.clone()));
+			vars.push(("UAIR_NEXT_NAME", next.name.clone()));
+		}
+		if let Some(finish) = env.finish {
+			let finish = match finish {
+				Finish::Natural => "natural",
+				Finish::Manual => "manual",
+			};
+			vars.push(("UAIR_FINISH", finish.into()));
+		}
+		Process {
+			program,
+			envs: vars,
+			timeout: self.command_timeout,
 		}
-		Ok(())
 	}
 }
 
+/// State of the timer which is passed to commands through `UAIR_` environment variables.
+#[derive(Clone, Copy)]
+pub struct Env<'s> {
+	pub progress: Progress<'s>,
+	pub finish: Option<Finish>,
+	pub config: &'s str,
+}
+
+/// Whether a session finished because its time ran out or because it was finished manually.
+#[derive(Clone, Copy)]
+pub enum Finish {
+	Natural,
+	Manual,
+}
+
+/// Command of a session, as specified in the config.
+pub enum SessionCommand {
+	/// Command line which is run through the shell.
+	Shell(String),
+	/// Program and its arguments, each of which is a format displayed when the command is run.
+	Argv(Vec<Vec<Token>>),
+}
+
+/// Command line which is run through the shell, or program which is run directly with the given
+/// arguments.
+pub enum Program {
+	Shell(String),
+	Argv(Vec<String>),
+}
+
+impl Program {
+	fn is_empty(&self) -> bool {
+		match self {
+			Program::Shell(command) => command.is_empty(),
+			Program::Argv(argv) => argv.is_empty(),
+		}
+	}
+
+	fn command(&self) -> Command {
+		match self {
+			Program::Shell(command) => {
+				let mut cmd = Command::new("/bin/sh");
+				cmd.arg("-c").arg(command);
+				cmd
+			}
+			Program::Argv(argv) => {
+				let mut cmd = Command::new(&argv[0]);
+				cmd.args(&argv[1..]);
+				cmd
+			}
+		}
+	}
+}
+
+impl Display for Program {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Program::Shell(command) => write!(f, "{}", command),
+			Program::Argv(argv) => write!(f, "{}", argv.join(" ")),
+		}
+	}
+}
+
+/// Program which is run with the given environment variables. Nothing is run if the command line
+/// or argument vector is empty.
+pub struct Process {
+	pub program: Program,
+	pub envs: Vec<(&'static str, String)>,
+	pub timeout: Option<Duration>,
+}
+
+impl Process {
+	/// Runs the program to completion, logging its exit status and standard error, or the error
+	/// preventing it from being run. The program is killed if it runs for longer than the timeout.
+	pub async fn run(self) {
+		if let Some(child) = self.start() {
+			self.wait(child).await
+		}
+	}
+
+	/// Starts the program and waits for it to complete in a separate thread, so that it is
+	/// started even if uair exits right after.
+	pub fn spawn(self) {
+		if let Some(child) = self.start() {
+			thread::spawn(move || async_io::block_on(self.wait(child)));
+		}
+	}
+
+	fn start(&self) -> Option<Child> {
+		if self.program.is_empty() {
+			return None;
+		}
+		self.program
+			.command()
+			.envs(self.envs.iter().map(|(k, v)| (k, v)))
+			.stdin(Stdio::null())
+			.stdout(Stdio::null())
+			.stderr(Stdio::piped())
+			.kill_on_drop(true)
+			.spawn()
+			.map_err(|err| error!("Could not run command \"{}\": {}", self.program, err))
+			.ok()
+	}
+
+	async fn wait(&self, child: Child) {
+		let output = async { Some(child.output().await) };
+		let output = match self.timeout {
+			Some(timeout) => {
+				output
+					.or(async {
+						Timer::after(timeout).await;
+						None
+					})
+					.await
+			}
+			None => output.await,
+		};
+		let output = match output {
+			Some(Ok(output)) => output,
+			Some(Err(err)) => {
+				error!("Could not wait for command \"{}\": {}", self.program, err);
+				return;
+			}
+			None => {
+				warn!(
+					"Command \"{}\" was killed after running for {}.",
+					self.program,
+					format_duration(self.timeout.unwrap_or_default())
+				);
+				return;
+			}
+		};
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		let stderr = stderr.trim_end();
+		if !stderr.is_empty() {
+			warn!(
+				"Command \"{}\" wrote to standard error: {}",
+				self.program, stderr
+			);
+		}
+		if output.status.success() {
+			info!(
+				"Command \"{}\" exited with {}.",
+				self.program, output.status
+			);
+		} else {
+			warn!(
+				"Command \"{}\" exited with {}.",
+				self.program, output.status
+			);
+		}
+	}
+}
+
+/// Commands which are run when the state of a session changes.
+#[derive(Clone, Default)]
+pub struct Hooks {
+	pub start: String,
+	pub pause: String,
+	pub resume: String,
+	pub skip: String,
+}
+
+/// Command which is run once the remaining time of a session reaches `at`.
+#[derive(Clone)]
+pub struct Warning {
+	pub at: Duration,
+	pub command: String,
+	pub text: String,
+}
+
+#[derive(Clone, Copy)]
+pub enum Hook {
+	Start,
+	Pause,
+	Resume,
+	Skip,
+}
+
 #[derive(Clone, Default)]
 pub struct Overridables {
 	pub format: Option<Vec<Token>>,
 	pub time_format: Option<Vec<TimeFormatToken>>,
+	pub clock_format: Option<String>,
+	pub tooltip_format: Option<Vec<Token>>,
+	pub bar_width: Option<usize>,
+	pub bar_fill: Option<String>,
+	pub bar_empty: Option<String>,
+	pub bar_partial: Option<String>,
 	pub paused_state_text: Option<String>,
 	pub resumed_state_text: Option<String>,
 }
@@ -74,37 +368,135 @@ impl Overridables {
 		Overridables::default()
 	}
 
-	pub fn format(self, format: &str) -> Self {
-		Overridables {
-			format: Some(Token::parse(format)),
-			..self
+	/// Overrides the format. In strict mode, unknown format specifiers are rejected.
+	pub fn format(self, format: &str, strict: bool) -> Result<Self, FormatError> {
+		let mut unknown = None;
+		let tokens = Token::parse(format, |err| {
+			unknown.get_or_insert(err);
+		})?;
+		match unknown {
+			Some(err) if strict => Err(err),
+			_ => Ok(Overridables {
+				format: Some(tokens),
+				..self
+			}),
 		}
 	}
 }
 
+/// State of the timer which is displayed alongside the session, but is not a property of it.
+#[derive(Clone, Copy, Default)]
+pub struct Progress<'s> {
+	pub index: usize,
+	pub count: usize,
+	pub iteration: u64,
+	pub iterations: Option<u64>,
+	pub next: Option<&'s Session>,
+	pub goal: Option<GoalStatus>,
+	/// Time for which the session has been resumed.
+	pub elapsed: Duration,
+}
+
+#[derive(Clone, Copy)]
 pub struct DisplayableSession<'s, const R: bool> {
 	session: &'s Session,
+	progress: Progress<'s>,
 	time: DisplayableTime<'s>,
 	format: &'s [Token],
+	clock_format: &'s str,
+	tooltip_format: &'s [Token],
+	bar: DisplayableBar<'s>,
 	pst_override: Option<&'s str>,
 	rst_override: Option<&'s str>,
+	markup: Markup,
+	color: bool,
+	json: bool,
 }
 
 impl<const R: bool> Display for DisplayableSession<'_, R> {
 	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-		for token in self.format {
+		if self.json {
+			let text = DisplayableSession {
+				json: false,
+				..*self
+			};
+			let tooltip = DisplayableSession {
+				format: self.tooltip_format,
+				..text
+			};
+			let output = WaybarOutput {
+				text: text.to_string().trim_end_matches('\n').into(),
+				tooltip: tooltip.to_string().trim_end_matches('\n').into(),
+				class: [&self.session.id, if R { "resumed" } else { "paused" }],
+				percentage: self.percent_done(),
+			};
+			let json = serde_json::to_string(&output).map_err(|_| fmt::Error)?;
+			return writeln!(f, "{}", json);
+		}
+		let mut w = MarkupWriter {
+			f,
+			markup: self.markup,
+			spans: 0,
+		};
+		self.write_tokens(&mut w, self.format)?;
+		w.finish()
+	}
+}
+
+impl<const R: bool> DisplayableSession<'_, R> {
+	fn write_tokens(&self, w: &mut MarkupWriter, tokens: &[Token]) -> fmt::Result {
+		for token in tokens {
 			match token {
-				Token::Name => write!(f, "{}", self.session.name)?,
+				Token::Name => write!(w, "{}", self.session.name)?,
 				Token::Percent => write!(
-					f,
+					w,
 					"{}",
 					(self.time.time.as_secs_f32() * 100.0 / self.session.duration.as_secs_f32())
 						as u8
 				)?,
-				Token::Time => write!(f, "{}", self.time)?,
-				Token::Total => write!(f, "{}", format_duration(self.session.duration))?,
+				Token::PercentDone => write!(w, "{}", self.percent_done())?,
+				Token::Time => write!(w, "{}", self.time)?,
+				Token::Elapsed => write!(w, "{}", self.time.with(self.elapsed()))?,
+				Token::Eta => write!(
+					w,
+					"{}",
+					(Local::now() + self.time.time).format(self.clock_format)
+				)?,
+				Token::Total => write!(w, "{}", format_duration(self.session.duration))?,
+				Token::Bar => self.bar.write(
+					w,
+					self.session.duration.saturating_sub(self.time.time),
+					self.session.duration,
+				)?,
+				Token::Index => write!(w, "{}", self.progress.index + 1)?,
+				Token::Count => write!(w, "{}", self.progress.count)?,
+				Token::Iter => write!(w, "{}", self.progress.iteration + 1)?,
+				Token::Iters => match self.progress.iterations {
+					Some(iterations) => write!(w, "{}", iterations)?,
+					None => write!(w, "∞")?,
+				},
+				Token::Warning => {
+					if let Some(warning) = self.session.warning(self.time.time) {
+						write!(w, "{}", warning.text)?
+					}
+				}
+				Token::NextName => {
+					if let Some(next) = self.progress.next {
+						write!(w, "{}", next.name)?
+					}
+				}
+				Token::GoalDone => match self.progress.goal {
+					Some(GoalStatus::Count(done, _)) => write!(w, "{}", done)?,
+					Some(GoalStatus::Duration(done, _)) => write!(w, "{}", self.time.with(done))?,
+					None => {}
+				},
+				Token::GoalTotal => match self.progress.goal {
+					Some(GoalStatus::Count(_, total)) => write!(w, "{}", total)?,
+					Some(GoalStatus::Duration(_, total)) => write!(w, "{}", self.time.with(total))?,
+					None => {}
+				},
 				Token::State => write!(
-					f,
+					w,
 					"{}",
 					if R {
 						self.rst_override
@@ -113,27 +505,239 @@ impl<const R: bool> Display for DisplayableSession<'_, R> {
 						self.pst_override.unwrap_or(&self.session.paused_state_text)
 					}
 				)?,
-				Token::Color(Color::Black) => write!(f, "\x1b[0;30m")?,
-				Token::Color(Color::Red) => write!(f, "\x1b[0;31m")?,
-				Token::Color(Color::Green) => write!(f, "\x1b[0;32m")?,
-				Token::Color(Color::Yellow) => write!(f, "\x1b[0;33m")?,
-				Token::Color(Color::Blue) => write!(f, "\x1b[0;34m")?,
-				Token::Color(Color::Purple) => write!(f, "\x1b[0;35m")?,
-				Token::Color(Color::Cyan) => write!(f, "\x1b[0;36m")?,
-				Token::Color(Color::White) => write!(f, "\x1b[0;37m")?,
-				Token::Color(Color::End) => write!(f, "\x1b[0m")?,
-				Token::Literal(literal) => write!(f, "{}", literal)?,
+				Token::Color(color) => {
+					if self.color {
+						w.color(color)?
+					}
+				}
+				Token::Literal(literal) => w.raw(literal)?,
+				Token::If(condition, then, otherwise) => {
+					if self.test(condition) {
+						self.write_tokens(w, then)?
+					} else {
+						self.write_tokens(w, otherwise)?
+					}
+				}
 			};
 		}
 		Ok(())
 	}
 }
 
+impl<const R: bool> DisplayableSession<'_, R> {
+	/// Sets the markup in which color and style format specifiers are displayed.
+	pub fn markup(self, markup: Markup) -> Self {
+		DisplayableSession { markup, ..self }
+	}
+
+	/// Controls whether color and style format specifiers are displayed.
+	pub fn color(self, color: bool) -> Self {
+		DisplayableSession { color, ..self }
+	}
+
+	/// Controls whether the session is displayed as a line containing a JSON object suitable for
+	/// custom modules of waybar, instead of as text in the format of the session.
+	pub fn json(self, json: bool) -> Self {
+		DisplayableSession { json, ..self }
+	}
+
+	fn percent_done(&self) -> u8 {
+		(self.elapsed().as_secs_f32() * 100.0 / self.total().as_secs_f32()) as u8
+	}
+
+	fn elapsed(&self) -> Duration {
+		self.progress.elapsed
+	}
+
+	/// Returns the time for which the session has been resumed and will be resumed, which differs
+	/// from its duration if time was added or subtracted.
+	fn total(&self) -> Duration {
+		self.elapsed() + self.time.time
+	}
+
+	fn test(&self, condition: &Condition) -> bool {
+		match condition {
+			Condition::Not(condition) => !self.test(condition),
+			Condition::Paused => !R,
+			Condition::Resumed => R,
+			Condition::Warning => self.session.warning(self.time.time).is_some(),
+			Condition::Id(op, id) => op.test(&self.session.id, id),
+			Condition::Remaining(op, duration) => op.test(&self.time.time, duration),
+			Condition::Elapsed(op, duration) => op.test(&self.elapsed(), duration),
+		}
+	}
+}
+
+/// Object which custom modules of waybar expect on every line of their output.
+#[derive(Serialize)]
+struct WaybarOutput<'s> {
+	text: String,
+	tooltip: String,
+	class: [&'s str; 2],
+	percentage: u8,
+}
+
+/// Writes displayed text in a markup language, escaping characters of dynamic text which are
+/// special in it. Literal text of the format is written as is, so that it can contain markup.
+struct MarkupWriter<'w, 'f> {
+	f: &'w mut Formatter<'f>,
+	markup: Markup,
+	/// Number of Pango spans which are still open.
+	spans: usize,
+}
+
+impl MarkupWriter<'_, '_> {
+	fn raw(&mut self, s: &str) -> fmt::Result {
+		self.f.write_str(s)
+	}
+
+	fn color(&mut self, color: &Color) -> fmt::Result {
+		match self.markup {
+			Markup::Ansi => color.write_ansi(self.f),
+			Markup::Pango => {
+				let attr = match color {
+					Color::Fg(paint) => format!("foreground=\"{}\"", paint.hex()),
+					Color::Bg(paint) => format!("background=\"{}\"", paint.hex()),
+					Color::Bold => "weight=\"bold\"".into(),
+					Color::Italic => "style=\"italic\"".into(),
+					Color::Underline => "underline=\"single\"".into(),
+					_ => {
+						self.finish()?;
+						match color.palette() {
+							Some(n) => format!("foreground=\"{}\"", Paint::Indexed(n).hex()),
+							None => return Ok(()),
+						}
+					}
+				};
+				self.spans += 1;
+				write!(self.f, "<span {}>", attr)
+			}
+			Markup::Polybar | Markup::Lemonbar => match color {
+				Color::Fg(paint) => write!(self.f, "%{{F{}}}", paint.hex()),
+				Color::Bg(paint) => write!(self.f, "%{{B{}}}", paint.hex()),
+				Color::Underline => write!(self.f, "%{{+u}}"),
+				Color::Bold | Color::Italic => Ok(()),
+				_ => {
+					write!(self.f, "%{{F-}}%{{B-}}%{{-u}}")?;
+					match color.palette() {
+						Some(n) => write!(self.f, "%{{F{}}}", Paint::Indexed(n).hex()),
+						None => Ok(()),
+					}
+				}
+			},
+			Markup::Tmux => match color {
+				Color::Fg(paint) => write!(self.f, "#[fg={}]", paint.tmux()),
+				Color::Bg(paint) => write!(self.f, "#[bg={}]", paint.tmux()),
+				Color::Bold => write!(self.f, "#[bold]"),
+				Color::Italic => write!(self.f, "#[italics]"),
+				Color::Underline => write!(self.f, "#[underscore]"),
+				_ => {
+					write!(self.f, "#[default]")?;
+					match color.palette() {
+						Some(n) => write!(self.f, "#[fg={}]", Paint::Indexed(n).tmux()),
+						None => Ok(()),
+					}
+				}
+			},
+			Markup::Plain => Ok(()),
+		}
+	}
+
+	/// Closes the Pango spans which are still open.
+	fn finish(&mut self) -> fmt::Result {
+		for _ in 0..mem::take(&mut self.spans) {
+			self.f.write_str("</span>")?;
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Write for MarkupWriter<'_, '_> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		if self.markup == Markup::Polybar {
+			return self.f.write_str(&s.replace("%{", "%%{"));
+		}
+		let escape = |c| match (self.markup, c) {
+			(Markup::Pango, '&') => Some("&amp;"),
+			(Markup::Pango, '<') => Some("&lt;"),
+			(Markup::Pango, '>') => Some("&gt;"),
+			(Markup::Tmux, '#') => Some("##"),
+			(Markup::Lemonbar, '%') => Some("%%"),
+			_ => None,
+		};
+		let mut start = 0;
+		for (i, c) in s.char_indices() {
+			if let Some(escaped) = escape(c) {
+				self.f.write_str(&s[start..i])?;
+				self.f.write_str(escaped)?;
+				start = i + c.len_utf8();
+			}
+		}
+		self.f.write_str(&s[start..])
+	}
+}
+
+#[derive(Clone)]
+pub struct Bar {
+	pub width: usize,
+	pub fill: String,
+	pub empty: String,
+	pub partial: String,
+}
+
+#[derive(Clone, Copy)]
+struct DisplayableBar<'s> {
+	width: usize,
+	fill: &'s str,
+	empty: &'s str,
+	partial: &'s str,
+}
+
+impl DisplayableBar<'_> {
+	/// Writes a bar filled in proportion to `elapsed`. Each cell is divided into one more step
+	/// than the number of partial characters, which fill the cell at the boundary.
+	fn write(&self, f: &mut impl fmt::Write, elapsed: Duration, total: Duration) -> fmt::Result {
+		let steps = self.partial.chars().count() + 1;
+		let fraction = if total.is_zero() {
+			1.0
+		} else {
+			(elapsed.as_secs_f64() / total.as_secs_f64()).min(1.0)
+		};
+		let filled = (fraction * (self.width * steps) as f64) as usize;
+		let (full, part) = (filled / steps, filled % steps);
+		for _ in 0..full {
+			write!(f, "{}", self.fill)?;
+		}
+		let mut cells = full;
+		if let Some(c) = part
+			.checked_sub(1)
+			.and_then(|i| self.partial.chars().nth(i))
+		{
+			write!(f, "{}", c)?;
+			cells += 1;
+		}
+		for _ in cells..self.width {
+			write!(f, "{}", self.empty)?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Copy)]
 struct DisplayableTime<'s> {
 	time: Duration,
 	format: &'s [TimeFormatToken],
 }
 
+impl<'s> DisplayableTime<'s> {
+	fn with(&self, time: Duration) -> DisplayableTime<'s> {
+		DisplayableTime {
+			time,
+			format: self.format,
+		}
+	}
+}
+
 impl Display for DisplayableTime<'_> {
 	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
 		let secs = self.time.as_secs();
@@ -189,16 +793,35 @@ impl Display for DisplayableTime<'_> {
 pub enum Token {
 	Name,
 	Percent,
+	PercentDone,
 	Time,
+	Elapsed,
+	Eta,
 	Total,
+	Bar,
 	State,
+	Index,
+	Count,
+	Iter,
+	Iters,
+	NextName,
+	Warning,
+	GoalDone,
+	GoalTotal,
 	Color(Color),
 	Literal(String),
+	If(Condition, Vec<Token>, Vec<Token>),
 }
 
 impl Token {
-	pub fn parse(format: &str) -> Vec<Token> {
+	/// Parses the format, passing every unknown format specifier, which is kept as literal text,
+	/// to `unknown`.
+	pub fn parse(
+		format: &str,
+		mut unknown: impl FnMut(FormatError),
+	) -> Result<Vec<Token>, FormatError> {
 		let mut tokens = Vec::new();
+		let mut blocks: Vec<Block> = Vec::new();
 		let mut k = 0;
 		let mut open = None;
 
@@ -206,24 +829,137 @@ impl Token {
 			match c {
 				'{' => open = Some(i),
 				'}' => {
-					if let Some(j) = open {
-						if let Ok(token) = format[j..=i].parse() {
-							if k != j {
-								tokens.push(Token::Literal(format[k..j].into()))
-							};
-							tokens.push(token);
-							k = i + 1;
+					let Some(j) = open.take() else {
+						continue;
+					};
+					let spec = &format[j..=i];
+					let token = match spec {
+						"{else}" | "{endif}" => None,
+						_ if spec.starts_with("{if:") => None,
+						_ => match spec.parse() {
+							Ok(token) => Some(token),
+							Err(_) => {
+								let msg = format!("unknown format specifier '{}'", spec);
+								unknown(FormatError::new(format, j, msg));
+								continue;
+							}
+						},
+					};
+					if k != j {
+						tokens.push(Token::Literal(format[k..j].into()))
+					};
+					k = i + 1;
+
+					if let Some(token) = token {
+						tokens.push(token);
+					} else if let Some(condition) = spec.strip_prefix("{if:") {
+						let condition = condition[..condition.len() - 1]
+							.parse()
+							.map_err(|msg| FormatError::new(format, j, msg))?;
+						blocks.push(Block {
+							condition,
+							pos: j,
+							outer: mem::take(&mut tokens),
+							then: None,
+						});
+					} else if spec == "{else}" {
+						match blocks.last_mut() {
+							Some(block) if block.then.is_none() => {
+								block.then = Some(mem::take(&mut tokens))
+							}
+							Some(_) => return Err(FormatError::new(format, j, "duplicate {else}")),
+							None => {
+								return Err(FormatError::new(format, j, "{else} without {if:...}"))
+							}
 						}
+					} else {
+						let Some(block) = blocks.pop() else {
+							return Err(FormatError::new(format, j, "{endif} without {if:...}"));
+						};
+						let inner = mem::replace(&mut tokens, block.outer);
+						let (then, otherwise) = match block.then {
+							Some(then) => (then, inner),
+							None => (inner, Vec::new()),
+						};
+						tokens.push(Token::If(block.condition, then, otherwise));
 					}
 				}
 				_ => {}
 			}
 		}
+		if let Some(block) = blocks.pop() {
+			return Err(FormatError::new(
+				format,
+				block.pos,
+				"{if:...} without {endif}",
+			));
+		}
 		if k != format.len() {
 			tokens.push(Token::Literal(format[k..].into()))
 		};
 
-		tokens
+		Ok(tokens)
+	}
+}
+
+struct Block {
+	condition: Condition,
+	pos: usize,
+	outer: Vec<Token>,
+	then: Option<Vec<Token>>,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("{msg} at position {pos}")]
+pub struct FormatError {
+	pos: usize,
+	msg: String,
+}
+
+impl FormatError {
+	/// Creates an error for the part of `format` starting at the byte offset `idx`. The position
+	/// is reported as a one-indexed character offset.
+	pub fn new(format: &str, idx: usize, msg: impl Into<String>) -> Self {
+		FormatError {
+			pos: format[..idx].chars().count() + 1,
+			msg: msg.into(),
+		}
+	}
+}
+
+#[derive(Clone)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub enum Condition {
+	Not(Box<Condition>),
+	Paused,
+	Resumed,
+	Warning,
+	Id(Op, String),
+	Remaining(Op, Duration),
+	Elapsed(Op, Duration),
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub enum Op {
+	Lt,
+	Le,
+	Gt,
+	Ge,
+	Eq,
+	Ne,
+}
+
+impl Op {
+	fn test<T: PartialOrd + ?Sized>(self, a: &T, b: &T) -> bool {
+		match self {
+			Op::Lt => a < b,
+			Op::Le => a <= b,
+			Op::Gt => a > b,
+			Op::Ge => a >= b,
+			Op::Eq => a == b,
+			Op::Ne => a != b,
+		}
 	}
 }
 
@@ -236,20 +972,33 @@ pub enum TimeFormatToken {
 }
 
 impl TimeFormatToken {
-	pub fn parse(mut format: &str) -> Vec<TimeFormatToken> {
-		let res: ModalResult<Vec<TimeFormatToken>> = repeat(
-			0..,
-			alt((
+	/// Parses the time format, passing every unknown time format specifier, which is kept as
+	/// literal text, to `unknown`.
+	pub fn parse(format: &str, mut unknown: impl FnMut(FormatError)) -> Vec<TimeFormatToken> {
+		let mut input = format;
+		let mut tokens = Vec::new();
+		while !input.is_empty() {
+			let idx = format.len() - input.len();
+			let res: ModalResult<TimeFormatToken> = alt((
 				preceded(
 					"%",
 					(opt(one_of('*')), opt(one_of(['-', '_', '0'])), opt(any)).map(Self::identify),
 				),
 				take_until(0.., "%").map(|s: &str| TimeFormatToken::Literal(s.into())),
-				(peek(any), rest).map(|(_, s): (char, &str)| TimeFormatToken::Literal(s.into())),
-			)),
-		)
-		.parse_next(&mut format);
-		res.unwrap()
+				rest.map(|s: &str| TimeFormatToken::Literal(s.into())),
+			))
+			.parse_next(&mut input);
+			let token = res.unwrap();
+			// Literal text only starts with '%' if `identify` does not recognize the specifier.
+			if let TimeFormatToken::Literal(literal) = &token {
+				if literal.starts_with('%') {
+					let msg = format!("unknown time format specifier '{}'", literal);
+					unknown(FormatError::new(format, idx, msg));
+				}
+			}
+			tokens.push(token);
+		}
+		tokens
 	}
 
 	fn identify((star, flag, spec): (Option<char>, Option<char>, Option<char>)) -> TimeFormatToken {
@@ -315,9 +1064,130 @@ pub enum Color {
 	Purple,
 	Cyan,
 	White,
+	BrightBlack,
+	BrightRed,
+	BrightGreen,
+	BrightYellow,
+	BrightBlue,
+	BrightPurple,
+	BrightCyan,
+	BrightWhite,
+	Fg(Paint),
+	Bg(Paint),
+	Bold,
+	Italic,
+	Underline,
 	End,
 }
 
+impl Color {
+	/// Returns the index of the color in the 16-color palette for the named colors.
+	fn palette(&self) -> Option<u8> {
+		match self {
+			Color::Black => Some(0),
+			Color::Red => Some(1),
+			Color::Green => Some(2),
+			Color::Yellow => Some(3),
+			Color::Blue => Some(4),
+			Color::Purple => Some(5),
+			Color::Cyan => Some(6),
+			Color::White => Some(7),
+			Color::BrightBlack => Some(8),
+			Color::BrightRed => Some(9),
+			Color::BrightGreen => Some(10),
+			Color::BrightYellow => Some(11),
+			Color::BrightBlue => Some(12),
+			Color::BrightPurple => Some(13),
+			Color::BrightCyan => Some(14),
+			Color::BrightWhite => Some(15),
+			_ => None,
+		}
+	}
+
+	fn write_ansi(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Color::Fg(paint) => write!(f, "\x1b[38;{}m", paint),
+			Color::Bg(paint) => write!(f, "\x1b[48;{}m", paint),
+			Color::Bold => write!(f, "\x1b[1m"),
+			Color::Italic => write!(f, "\x1b[3m"),
+			Color::Underline => write!(f, "\x1b[4m"),
+			Color::End => write!(f, "\x1b[0m"),
+			_ => match self.palette() {
+				Some(n @ 0..8) => write!(f, "\x1b[0;3{}m", n),
+				Some(n) => write!(f, "\x1b[0;9{}m", n - 8),
+				None => Ok(()),
+			},
+		}
+	}
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub enum Paint {
+	Indexed(u8),
+	Rgb(u8, u8, u8),
+}
+
+impl Paint {
+	/// Returns the red, green and blue components of the color, with indexed colors taken from
+	/// the default palette of xterm.
+	fn rgb(self) -> (u8, u8, u8) {
+		const BASIC: [(u8, u8, u8); 16] = [
+			(0x00, 0x00, 0x00),
+			(0xcd, 0x00, 0x00),
+			(0x00, 0xcd, 0x00),
+			(0xcd, 0xcd, 0x00),
+			(0x00, 0x00, 0xee),
+			(0xcd, 0x00, 0xcd),
+			(0x00, 0xcd, 0xcd),
+			(0xe5, 0xe5, 0xe5),
+			(0x7f, 0x7f, 0x7f),
+			(0xff, 0x00, 0x00),
+			(0x00, 0xff, 0x00),
+			(0xff, 0xff, 0x00),
+			(0x5c, 0x5c, 0xff),
+			(0xff, 0x00, 0xff),
+			(0x00, 0xff, 0xff),
+			(0xff, 0xff, 0xff),
+		];
+		const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+		match self {
+			Paint::Rgb(r, g, b) => (r, g, b),
+			Paint::Indexed(n @ 0..16) => BASIC[n as usize],
+			Paint::Indexed(n @ 16..232) => {
+				let n = (n - 16) as usize;
+				(LEVELS[n / 36], LEVELS[n / 6 % 6], LEVELS[n % 6])
+			}
+			Paint::Indexed(n) => {
+				let gray = 8 + 10 * (n - 232);
+				(gray, gray, gray)
+			}
+		}
+	}
+
+	fn hex(self) -> String {
+		let (r, g, b) = self.rgb();
+		format!("#{:02x}{:02x}{:02x}", r, g, b)
+	}
+
+	fn tmux(self) -> String {
+		match self {
+			Paint::Indexed(n) => format!("colour{}", n),
+			Paint::Rgb(..) => self.hex(),
+		}
+	}
+}
+
+/// Displays the parameters of the SGR sequence selecting the color.
+impl Display for Paint {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Paint::Indexed(n) => write!(f, "5;{}", n),
+			Paint::Rgb(r, g, b) => write!(f, "2;{};{};{}", r, g, b),
+		}
+	}
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct SessionId {
 	index: usize,
@@ -328,10 +1198,10 @@ pub struct SessionId {
 }
 
 impl SessionId {
-	pub fn new(sessions: &[Session], iterations: Option<u64>) -> Self {
+	pub fn new(schedule: &[usize], iterations: Option<u64>) -> Self {
 		SessionId {
 			index: 0,
-			len: sessions.len(),
+			len: schedule.len(),
 			infinite: iterations.is_none(),
 			iter_no: 0,
 			total_iter: iterations.unwrap_or(0),
@@ -386,12 +1256,20 @@ impl SessionId {
 
 #[cfg(test)]
 mod tests {
-	use super::{Color, Numeric, Pad, TimeFormatToken, Token};
+	use super::{
+		Bar, Color, Condition, DisplayableBar, Hooks, MarkupWriter, Numeric, Op, Pad, Paint,
+		Process, Program, Progress, Session, SessionCommand, TimeFormatToken, Token, Warning,
+	};
+	use log::Level;
+	use std::collections::HashMap;
+	use std::fmt::{self, Display, Formatter, Write as _};
+	use std::time::Duration;
+	use uair::Markup;
 
 	#[test]
 	fn parse_format() {
 		assert_eq!(
-			&Token::parse("{cyan}{time}{end}\n"),
+			&Token::parse("{cyan}{time}{end}\n", |_| {}).unwrap(),
 			&[
 				Token::Color(Color::Cyan),
 				Token::Time,
@@ -400,7 +1278,7 @@ mod tests {
 			]
 		);
 		assert_eq!(
-			&Token::parse("String with {time} with some text ahead."),
+			&Token::parse("String with {time} with some text ahead.", |_| {}).unwrap(),
 			&[
 				Token::Literal("String with ".into()),
 				Token::Time,
@@ -408,19 +1286,238 @@ mod tests {
 			]
 		);
 		assert_eq!(
-			&Token::parse("}}{}{{}{}}}{{}{{}}}"),
+			&Token::parse("}}{}{{}{}}}{{}{{}}}", |_| {}).unwrap(),
 			&[Token::Literal("}}{}{{}{}}}{{}{{}}}".into())]
 		);
 		assert_eq!(
-			&Token::parse("{time} text {time}"),
+			&Token::parse("{time} text {time}", |_| {}).unwrap(),
 			&[Token::Time, Token::Literal(" text ".into()), Token::Time,]
 		);
 	}
 
+	#[test]
+	fn parse_conditional_format() {
+		assert_eq!(
+			&Token::parse(
+				"{if:paused}P {else}{if:remaining<5m}!{endif}{endif}{time}",
+				|_| {}
+			)
+			.unwrap(),
+			&[
+				Token::If(
+					Condition::Paused,
+					vec![Token::Literal("P ".into())],
+					vec![Token::If(
+						Condition::Remaining(Op::Lt, Duration::from_secs(300)),
+						vec![Token::Literal("!".into())],
+						vec![],
+					)],
+				),
+				Token::Time,
+			]
+		);
+		assert_eq!(
+			&Token::parse("{if:!id=work}x{endif}", |_| {}).unwrap(),
+			&[Token::If(
+				Condition::Not(Box::new(Condition::Id(Op::Eq, "work".into()))),
+				vec![Token::Literal("x".into())],
+				vec![],
+			)]
+		);
+		assert_eq!(
+			Token::parse("{time}{endif}", |_| {})
+				.unwrap_err()
+				.to_string(),
+			"{endif} without {if:...} at position 7"
+		);
+		assert_eq!(
+			Token::parse("{if:paused}{time}", |_| {})
+				.unwrap_err()
+				.to_string(),
+			"{if:...} without {endif} at position 1"
+		);
+		assert!(Token::parse("{if:remaining<soon}{endif}", |_| {}).is_err());
+	}
+
+	#[test]
+	fn parse_style_format() {
+		assert_eq!(
+			&Token::parse(
+				"{fg:#ff8800}{bg:208}{fg:bright_red}{bold}{reset}{fg:#ff88}",
+				|_| {}
+			)
+			.unwrap(),
+			&[
+				Token::Color(Color::Fg(Paint::Rgb(255, 136, 0))),
+				Token::Color(Color::Bg(Paint::Indexed(208))),
+				Token::Color(Color::Fg(Paint::Indexed(9))),
+				Token::Color(Color::Bold),
+				Token::Color(Color::End),
+				Token::Literal("{fg:#ff88}".into()),
+			]
+		);
+	}
+
+	#[test]
+	fn display_bar() {
+		struct Bar<'s>(DisplayableBar<'s>, u64);
+		impl Display for Bar<'_> {
+			fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+				self.0
+					.write(f, Duration::from_secs(self.1), Duration::from_secs(80))
+			}
+		}
+		let bar = |partial| DisplayableBar {
+			width: 4,
+			fill: "#",
+			empty: "-",
+			partial,
+		};
+		assert_eq!(Bar(bar(""), 0).to_string(), "----");
+		assert_eq!(Bar(bar(""), 50).to_string(), "##--");
+		assert_eq!(Bar(bar(""), 100).to_string(), "####");
+		assert_eq!(Bar(bar("▎▌▊"), 50).to_string(), "##▌-");
+		assert_eq!(Bar(bar("▎▌▊"), 79).to_string(), "###▊");
+	}
+
+	#[test]
+	fn display_markup() {
+		struct Styled(Markup);
+		impl Display for Styled {
+			fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+				let mut w = MarkupWriter {
+					f,
+					markup: self.0,
+					spans: 0,
+				};
+				w.color(&Color::Bold)?;
+				w.color(&Color::Fg(Paint::Indexed(196)))?;
+				w.write_str("<a & #b>")?;
+				w.color(&Color::Red)?;
+				w.raw("%{O10}")?;
+				w.write_str("%{c}")?;
+				w.finish()
+			}
+		}
+		assert_eq!(
+			Styled(Markup::Ansi).to_string(),
+			"\x1b[1m\x1b[38;5;196m<a & #b>\x1b[0;31m%{O10}%{c}"
+		);
+		assert_eq!(
+			Styled(Markup::Pango).to_string(),
+			"<span weight=\"bold\"><span foreground=\"#ff0000\">&lt;a &amp; #b&gt;</span></span>\
+			 <span foreground=\"#cd0000\">%{O10}%{c}</span>"
+		);
+		assert_eq!(
+			Styled(Markup::Polybar).to_string(),
+			"%{F#ff0000}<a & #b>%{F-}%{B-}%{-u}%{F#cd0000}%{O10}%%{c}"
+		);
+		assert_eq!(
+			Styled(Markup::Lemonbar).to_string(),
+			"%{F#ff0000}<a & #b>%{F-}%{B-}%{-u}%{F#cd0000}%{O10}%%{c}"
+		);
+		assert_eq!(
+			Styled(Markup::Tmux).to_string(),
+			"#[bold]#[fg=colour196]<a & ##b>#[default]#[fg=colour1]%{O10}%{c}"
+		);
+		assert_eq!(Styled(Markup::Plain).to_string(), "<a & #b>%{O10}%{c}");
+	}
+
+	fn session() -> Session {
+		Session {
+			id: "work".into(),
+			name: "Work <1>".into(),
+			duration: Duration::from_secs(100),
+			command: SessionCommand::Shell("".into()),
+			command_timeout: None,
+			command_blocking: false,
+			format: Token::parse("{red}{name}{end}\n", |_| {}).unwrap(),
+			time_format: TimeFormatToken::parse("%M:%S", |_| {}),
+			clock_format: "%H:%M".into(),
+			tooltip_format: Token::parse("{name}: {time}", |_| {}).unwrap(),
+			bar: Bar {
+				width: 10,
+				fill: "#".into(),
+				empty: "-".into(),
+				partial: "".into(),
+			},
+			hooks: Hooks::default(),
+			warnings: Vec::new(),
+			autostart: false,
+			paused_state_text: "".into(),
+			resumed_state_text: "".into(),
+			overrides: HashMap::new(),
+		}
+	}
+
+	fn progress(elapsed: u64) -> Progress<'static> {
+		Progress {
+			elapsed: Duration::from_secs(elapsed),
+			..Progress::default()
+		}
+	}
+
+	#[test]
+	fn display_elapsed() {
+		let session = Session {
+			format: Token::parse("{elapsed} {percent_done} {bar}", |_| {}).unwrap(),
+			..session()
+		};
+		// 50 seconds of a 100 second session elapsed after 100 seconds were added. The bar is
+		// drawn from the remaining time and the duration only.
+		assert_eq!(
+			session
+				.display::<true>(Duration::from_secs(150), progress(50), None)
+				.to_string(),
+			"00:50 25 ----------"
+		);
+		assert_eq!(
+			session
+				.display::<true>(Duration::from_secs(50), progress(0), None)
+				.to_string(),
+			"00:00 0 #####-----"
+		);
+	}
+
+	#[test]
+	fn display_json() {
+		assert_eq!(
+			session()
+				.display::<false>(Duration::from_secs(75), progress(25), None)
+				.markup(Markup::Pango)
+				.json(true)
+				.to_string(),
+			r##"{"text":"<span foreground=\"#cd0000\">Work &lt;1&gt;</span>","tooltip":"Work &lt;1&gt;: 01:15","class":["work","paused"],"percentage":25}"##
+				.to_owned() + "\n"
+		);
+	}
+
+	#[test]
+	fn display_warning() {
+		let warning = |at, text: &str| Warning {
+			at: Duration::from_secs(at),
+			command: "".into(),
+			text: text.into(),
+		};
+		let session = Session {
+			format: Token::parse("{if:warning}!{endif}{warning}", |_| {}).unwrap(),
+			warnings: vec![warning(60, "1m left"), warning(10, "10s left")],
+			..session()
+		};
+		let display = |remaining| {
+			session
+				.display::<true>(Duration::from_secs(remaining), Progress::default(), None)
+				.to_string()
+		};
+		assert_eq!(display(75), "");
+		assert_eq!(display(60), "!1m left");
+		assert_eq!(display(5), "!10s left");
+	}
+
 	#[test]
 	fn parse_time_format() {
 		assert_eq!(
-			&TimeFormatToken::parse("%H:%M:%S"),
+			&TimeFormatToken::parse("%H:%M:%S", |_| {}),
 			&[
 				TimeFormatToken::Numeric(Numeric::Hour, Pad::Zero, false),
 				TimeFormatToken::Literal(":".into()),
@@ -430,7 +1527,7 @@ mod tests {
 			]
 		);
 		assert_eq!(
-			&TimeFormatToken::parse("%L:%M:%S"),
+			&TimeFormatToken::parse("%L:%M:%S", |_| {}),
 			&[
 				TimeFormatToken::Literal("%L".into()),
 				TimeFormatToken::Literal(":".into()),
@@ -440,7 +1537,7 @@ mod tests {
 			]
 		);
 		assert_eq!(
-			&TimeFormatToken::parse("%H:%M:%"),
+			&TimeFormatToken::parse("%H:%M:%", |_| {}),
 			&[
 				TimeFormatToken::Numeric(Numeric::Hour, Pad::Zero, false),
 				TimeFormatToken::Literal(":".into()),
@@ -450,7 +1547,7 @@ mod tests {
 			]
 		);
 		assert_eq!(
-			&TimeFormatToken::parse("%_H:%-M:%S"),
+			&TimeFormatToken::parse("%_H:%-M:%S", |_| {}),
 			&[
 				TimeFormatToken::Numeric(Numeric::Hour, Pad::Space, false),
 				TimeFormatToken::Literal(":".into()),
@@ -460,7 +1557,7 @@ mod tests {
 			]
 		);
 		assert_eq!(
-			&TimeFormatToken::parse("%H:%*-M:%S"),
+			&TimeFormatToken::parse("%H:%*-M:%S", |_| {}),
 			&[
 				TimeFormatToken::Numeric(Numeric::Hour, Pad::Zero, false),
 				TimeFormatToken::Literal(":".into()),
@@ -470,7 +1567,7 @@ mod tests {
 			]
 		);
 		assert_eq!(
-			&TimeFormatToken::parse("%*-Hh %*-Mm %-Ss"),
+			&TimeFormatToken::parse("%*-Hh %*-Mm %-Ss", |_| {}),
 			&[
 				TimeFormatToken::Numeric(Numeric::Hour, Pad::None, true),
 				TimeFormatToken::Literal("h ".into()),
@@ -481,4 +1578,46 @@ mod tests {
 			]
 		);
 	}
+
+	#[test]
+	fn shell_logs_exit_status_and_timeout() {
+		testing_logger::setup();
+		async_io::block_on(async {
+			Process {
+				program: Program::Argv(vec!["true".into()]),
+				envs: Vec::new(),
+				timeout: None,
+			}
+			.run()
+			.await;
+			Process {
+				program: Program::Shell("echo oops >&2; exit 3".into()),
+				envs: Vec::new(),
+				timeout: None,
+			}
+			.run()
+			.await;
+			Process {
+				program: Program::Argv(vec!["sleep".into(), "5".into()]),
+				envs: Vec::new(),
+				timeout: Some(Duration::from_millis(50)),
+			}
+			.run()
+			.await;
+		});
+		testing_logger::validate(|captured_logs| {
+			let bodies: Vec<_> = captured_logs.iter().map(|log| log.body.as_str()).collect();
+			assert_eq!(
+				bodies,
+				[
+					"Command \"true\" exited with exit status: 0.",
+					"Command \"echo oops >&2; exit 3\" wrote to standard error: oops",
+					"Command \"echo oops >&2; exit 3\" exited with exit status: 3.",
+					"Command \"sleep 5\" was killed after running for 50ms.",
+				]
+			);
+			let levels: Vec<_> = captured_logs.iter().map(|log| log.level).collect();
+			assert_eq!(levels, [Level::Info, Level::Warn, Level::Warn, Level::Warn]);
+		});
+	}
 }
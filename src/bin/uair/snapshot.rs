@@ -0,0 +1,136 @@
+use crate::config::Config;
+use crate::history::{SavedTracker, Tracker};
+use crate::session::SessionId;
+use crate::timer::State;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+#[derive(Serialize, Deserialize, PartialEq)]
+pub struct Snapshot {
+	id: String,
+	position: usize,
+	iteration: u64,
+	started: bool,
+	resumed: bool,
+	#[serde(with = "humantime_serde")]
+	remaining: Duration,
+	#[serde(default)]
+	tracker: SavedTracker,
+}
+
+impl Snapshot {
+	pub fn new(
+		config: &Config,
+		sid: &SessionId,
+		started: bool,
+		tracker: &Tracker,
+		state: &State,
+		now: Instant,
+	) -> Option<Snapshot> {
+		let (resumed, remaining) = match *state {
+			State::Paused(duration) => (false, duration),
+			State::Resumed(_, dest) => (true, dest.saturating_duration_since(now)),
+			State::PreInit | State::Finished => return None,
+		};
+		Some(Snapshot {
+			id: config.sessions[config.schedule[sid.curr()]].id.clone(),
+			position: sid.curr(),
+			iteration: sid.iter_no,
+			started,
+			resumed,
+			remaining: Duration::from_millis(remaining.as_millis() as u64),
+			tracker: tracker.save(now),
+		})
+	}
+
+	pub fn load(path: &Path) -> io::Result<Option<Snapshot>> {
+		match fs::read_to_string(path) {
+			Ok(data) => Ok(Some(serde_json::from_str(&data)?)),
+			Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+			Err(err) => Err(err),
+		}
+	}
+
+	pub fn save(&self, path: &Path) -> io::Result<()> {
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		fs::write(path, serde_json::to_vec(self)?)
+	}
+
+	pub fn remove(path: &Path) -> io::Result<()> {
+		match fs::remove_file(path) {
+			Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+			_ => Ok(()),
+		}
+	}
+
+	/// Returns the session id, whether the session has been started, the state of the timer and
+	/// the paused tracker of the session described by the snapshot, or `None` if it no longer
+	/// matches the config.
+	pub fn restore(
+		&self,
+		config: &Config,
+		sid: SessionId,
+	) -> Option<(SessionId, bool, State, Tracker)> {
+		let position = if config
+			.schedule
+			.get(self.position)
+			.is_some_and(|&idx| config.sessions[idx].id == self.id)
+		{
+			self.position
+		} else {
+			config
+				.idmap
+				.get(&self.id)
+				.and_then(|&idx| config.position(idx, 0))?
+		};
+
+		let mut sid = sid.jump(position);
+		if self.iteration < sid.total_iter {
+			sid.iter_no = self.iteration;
+		}
+
+		let state = if self.resumed {
+			let start = Instant::now();
+			State::Resumed(start, start + self.remaining)
+		} else {
+			State::Paused(self.remaining)
+		};
+		Some((sid, self.started, state, Tracker::restore(&self.tracker)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::ConfigBuilder;
+
+	#[test]
+	fn restore_moved_session() {
+		let config = |conf| ConfigBuilder::deserialize(conf).unwrap().build().unwrap();
+		let old = config("[[sessions]]\nid = \"work\"\n[[sessions]]\nid = \"rest\"\n");
+		let new = config("[[sessions]]\nid = \"rest\"\n[[sessions]]\nid = \"work\"\n");
+
+		let sid = SessionId::new(&old.schedule, old.iterations).jump(1);
+		let state = State::Paused(Duration::from_secs(90));
+		let mut tracker = Tracker::default();
+		tracker.resume();
+		tracker.pause();
+		let snapshot = Snapshot::new(&old, &sid, true, &tracker, &state, Instant::now()).unwrap();
+
+		let sid = SessionId::new(&new.schedule, new.iterations);
+		let (sid, started, state, restored) = snapshot.restore(&new, sid).unwrap();
+		assert_eq!(sid.curr(), 0);
+		assert!(started);
+		assert!(matches!(state, State::Paused(d) if d == Duration::from_secs(90)));
+		assert!(restored.save(Instant::now()) == tracker.save(Instant::now()));
+
+		let gone = config("[[sessions]]\nid = \"work\"\n");
+		let sid = SessionId::new(&gone.schedule, gone.iterations);
+		assert!(snapshot.restore(&gone, sid).is_none());
+	}
+}
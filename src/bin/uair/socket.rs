@@ -2,8 +2,9 @@ use async_net::unix::{UnixListener, UnixStream};
 use futures_lite::io::BlockOn;
 use futures_lite::{AsyncReadExt, AsyncWriteExt};
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, ErrorKind, Write};
 use std::path::PathBuf;
+use uair::{Reply, HEADER_LEN, MAX_FRAME_LEN};
 
 pub struct Listener {
 	path: PathBuf,
@@ -20,7 +21,10 @@ impl Listener {
 
 	pub async fn listen(&self) -> io::Result<Stream> {
 		let (stream, _) = self.listener.accept().await?;
-		Ok(Stream { stream })
+		Ok(Stream {
+			stream,
+			json: false,
+		})
 	}
 }
 
@@ -32,12 +36,55 @@ impl Drop for Listener {
 
 pub struct Stream {
 	stream: UnixStream,
+	json: bool,
 }
 
 impl Stream {
 	pub async fn read<'buf>(&mut self, buffer: &'buf mut Vec<u8>) -> io::Result<&'buf [u8]> {
-		let n_bytes = self.stream.read_to_end(buffer).await?;
-		Ok(&buffer[..n_bytes])
+		let mut header = [0; HEADER_LEN];
+		self.stream.read_exact(&mut header[..1]).await?;
+		if header[0] == b'{' {
+			self.json = true;
+			buffer.clear();
+			buffer.push(b'{');
+			return self.read_line(buffer).await;
+		}
+
+		self.stream.read_exact(&mut header[1..]).await?;
+		uair::check_header(&header).map_err(|msg| io::Error::new(ErrorKind::InvalidData, msg))?;
+
+		let mut len = [0; 4];
+		self.stream.read_exact(&mut len).await?;
+		let len = u32::from_be_bytes(len) as usize;
+		if len > MAX_FRAME_LEN {
+			return Err(io::Error::new(ErrorKind::InvalidData, "frame is too large"));
+		}
+		buffer.resize(len, 0);
+		self.stream.read_exact(buffer).await?;
+		Ok(buffer)
+	}
+
+	async fn read_line<'buf>(&mut self, buffer: &'buf mut Vec<u8>) -> io::Result<&'buf [u8]> {
+		let mut chunk = [0; 1024];
+		while !buffer.contains(&b'\n') {
+			let n_bytes = self.stream.read(&mut chunk).await?;
+			if n_bytes == 0 {
+				break;
+			}
+			buffer.extend_from_slice(&chunk[..n_bytes]);
+			if buffer.len() > MAX_FRAME_LEN {
+				return Err(io::Error::new(ErrorKind::InvalidData, "line is too long"));
+			}
+		}
+		let len = buffer
+			.iter()
+			.position(|&b| b == b'\n')
+			.unwrap_or(buffer.len());
+		Ok(&buffer[..len])
+	}
+
+	pub fn is_json(&self) -> bool {
+		self.json
 	}
 
 	pub async fn write(&mut self, data: &[u8]) -> io::Result<()> {
@@ -45,15 +92,22 @@ impl Stream {
 		Ok(())
 	}
 
+	pub async fn reply(&mut self, reply: &Reply) -> io::Result<()> {
+		let data = encode_reply(reply, self.json)?;
+		self.write(&data).await
+	}
+
 	pub fn into_blocking(self) -> BlockingStream {
 		BlockingStream {
 			stream: BlockOn::new(self.stream),
+			json: self.json,
 		}
 	}
 }
 
 pub struct BlockingStream {
 	stream: BlockOn<UnixStream>,
+	json: bool,
 }
 
 impl BlockingStream {
@@ -62,4 +116,19 @@ impl BlockingStream {
 		self.stream.flush()?;
 		Ok(())
 	}
+
+	pub fn reply(&mut self, reply: &Reply) -> io::Result<()> {
+		let data = encode_reply(reply, self.json)?;
+		self.write(&data)
+	}
+}
+
+fn encode_reply(reply: &Reply, json: bool) -> io::Result<Vec<u8>> {
+	if json {
+		let mut line = serde_json::to_vec(reply)?;
+		line.push(b'\n');
+		Ok(line)
+	} else {
+		uair::encode_frame(reply).map_err(io::Error::other)
+	}
 }
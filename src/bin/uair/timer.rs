@@ -1,30 +1,45 @@
 use crate::app::Event;
-use crate::session::Session;
+use crate::session::{Env, Progress, Session};
 use crate::socket::BlockingStream;
 use crate::Error;
 use async_io::Timer;
 use std::fmt::Write as _;
 use std::io::{self, Stdout, Write};
 use std::time::{Duration, Instant};
+use uair::{ListenArgs, Markup, Notification, Reply};
 
 pub struct UairTimer {
 	interval: Duration,
 	pub writer: Writer,
 	pub state: State,
+	/// Number of warnings of the current session which have been reached.
+	warned: usize,
 }
 
 impl UairTimer {
-	pub fn new(interval: Duration, quiet: bool) -> Self {
+	pub fn new(interval: Duration, quiet: bool, markup: Markup, json: bool, color: bool) -> Self {
 		UairTimer {
 			interval,
-			writer: Writer::new(quiet),
+			writer: Writer::new(quiet, markup, json, color),
 			state: State::PreInit,
+			warned: 0,
 		}
 	}
 
+	/// Treats the warnings of the session whose thresholds are not less than `remaining` as
+	/// already reached, so that only warnings crossed from now on are run.
+	pub fn reset_warnings(&mut self, session: &Session, remaining: Duration) {
+		self.warned = session
+			.warnings
+			.iter()
+			.take_while(|w| w.at >= remaining)
+			.count();
+	}
+
 	pub async fn start(
 		&mut self,
 		session: &Session,
+		env: Env<'_>,
 		start: Instant,
 		dest: Instant,
 	) -> Result<Event, Error> {
@@ -36,7 +51,20 @@ impl UairTimer {
 
 		while end <= dest {
 			Timer::at(end).await;
-			self.writer.write::<true>(session, dest - end)?;
+			let remaining = dest - end;
+			let progress = Progress {
+				elapsed: env.progress.elapsed + (end - start),
+				..env.progress
+			};
+			while let Some(warning) = session
+				.warnings
+				.get(self.warned)
+				.filter(|w| remaining <= w.at)
+			{
+				self.warned += 1;
+				session.run_warning(warning, &Env { progress, ..env });
+			}
+			self.writer.write::<true>(session, progress, remaining)?;
 			end += self.interval;
 		}
 
@@ -45,27 +73,44 @@ impl UairTimer {
 }
 
 pub struct Writer {
-	streams: Vec<(BlockingStream, Option<String>)>,
+	streams: Vec<Output>,
+	subscribers: Vec<BlockingStream>,
 	stdout: Option<Stdout>,
 	buf: String,
+	markup: Markup,
+	json: bool,
+	color: bool,
 }
 
 impl Writer {
-	fn new(quiet: bool) -> Self {
+	fn new(quiet: bool, markup: Markup, json: bool, color: bool) -> Self {
 		Writer {
 			streams: Vec::new(),
+			subscribers: Vec::new(),
 			stdout: (!quiet).then(io::stdout),
 			buf: "".into(),
+			markup,
+			json,
+			color,
 		}
 	}
 
 	pub fn write<const R: bool>(
 		&mut self,
 		session: &Session,
+		progress: Progress<'_>,
 		duration: Duration,
 	) -> Result<(), Error> {
 		if let Some(stdout) = &mut self.stdout {
-			_ = write!(self.buf, "{}", session.display::<R>(duration, None));
+			_ = write!(
+				self.buf,
+				"{}",
+				session
+					.display::<R>(duration, progress, None)
+					.markup(self.markup)
+					.color(self.color)
+					.json(self.json)
+			);
 			if write!(stdout, "{}", self.buf)
 				.and_then(|_| stdout.flush())
 				.is_err()
@@ -74,21 +119,50 @@ impl Writer {
 			}
 			self.buf.clear();
 		}
-		self.streams.retain_mut(|(stream, overrid)| {
-			let overrid = overrid.as_ref().and_then(|o| session.overrides.get(o));
-			_ = write!(self.buf, "{}\0", session.display::<R>(duration, overrid));
-			let res = stream.write(self.buf.as_bytes()).is_ok();
-			self.buf.clear();
-			res
+		self.streams.retain_mut(|output| {
+			let overrid = output
+				.overrid
+				.as_ref()
+				.and_then(|o| session.overrides.get(o));
+			let displayed = session
+				.display::<R>(duration, progress, overrid)
+				.markup(output.markup)
+				.color(self.color)
+				.json(output.json)
+				.to_string();
+			output.stream.reply(&Reply::Output(displayed)).is_ok()
 		});
 		Ok(())
 	}
 
-	pub fn add_stream(&mut self, stream: BlockingStream, overrid: Option<String>) {
-		self.streams.push((stream, overrid));
+	pub fn add_stream(&mut self, stream: BlockingStream, args: ListenArgs) {
+		self.streams.push(Output {
+			stream,
+			overrid: args.overrid,
+			markup: args.markup.unwrap_or(self.markup),
+			json: args.json,
+		});
+	}
+
+	pub fn notify(&mut self, notification: Notification) {
+		let reply = Reply::Notification(notification);
+		self.subscribers
+			.retain_mut(|stream| stream.reply(&reply).is_ok());
+	}
+
+	pub fn add_subscriber(&mut self, stream: BlockingStream) {
+		self.subscribers.push(stream);
 	}
 }
 
+/// Stream of a listening client, along with the way in which it wants the session displayed.
+struct Output {
+	stream: BlockingStream,
+	overrid: Option<String>,
+	markup: Markup,
+	json: bool,
+}
+
 pub enum State {
 	PreInit,
 	Paused(Duration),
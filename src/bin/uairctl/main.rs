@@ -1,48 +1,92 @@
+mod stats;
+
 use argh::FromArgs;
-use std::io::{self, BufRead, BufReader, Read, Write};
+use stats::StatsArgs;
+use std::io::{self, BufReader, ErrorKind, Read, Write};
 use std::net::Shutdown;
 use std::os::unix::net::UnixStream;
-use std::str;
-use uair::{get_socket_path, Command, FetchArgs};
+use std::process::ExitCode;
+use uair::{
+	get_socket_path, AddTimeArgs, Command, FetchArgs, FinishArgs, JumpArgs, ListenArgs, NextArgs,
+	PauseArgs, PrevArgs, ReloadArgs, Reply, RestartArgs, ResumeArgs, StatusArgs, SubscribeArgs,
+	SubtractTimeArgs, ToggleArgs,
+};
 
-fn main() -> Result<(), Error> {
-	let mut args: Args = argh::from_env();
-	if let Command::Fetch(FetchArgs { format }) = &mut args.command {
+fn main() -> ExitCode {
+	if let Err(err) = run() {
+		_ = writeln!(io::stderr(), "{}", err);
+		return ExitCode::FAILURE;
+	}
+	ExitCode::SUCCESS
+}
+
+fn run() -> Result<(), Error> {
+	let args: Args = argh::from_env();
+	let mut command = match args.command {
+		Subcommand::Stats(stats_args) => return stats::run(&stats_args),
+		Subcommand::Pause(args) => Command::Pause(args),
+		Subcommand::Resume(args) => Command::Resume(args),
+		Subcommand::Toggle(args) => Command::Toggle(args),
+		Subcommand::Next(args) => Command::Next(args),
+		Subcommand::Prev(args) => Command::Prev(args),
+		Subcommand::Finish(args) => Command::Finish(args),
+		Subcommand::Jump(args) => Command::Jump(args),
+		Subcommand::Reload(args) => Command::Reload(args),
+		Subcommand::Fetch(args) => Command::Fetch(args),
+		Subcommand::Listen(args) => Command::Listen(args),
+		Subcommand::Status(args) => Command::Status(args),
+		Subcommand::Subscribe(args) => Command::Subscribe(args),
+		Subcommand::AddTime(args) => Command::AddTime(args),
+		Subcommand::SubtractTime(args) => Command::SubtractTime(args),
+		Subcommand::Restart(args) => Command::Restart(args),
+	};
+	if let Command::Fetch(FetchArgs { format, .. }) = &mut command {
 		*format = unescape(format);
 	}
 
-	let command = bincode::serialize(&args.command)?;
 	let mut stream = UnixStream::connect(&args.socket)?;
 
-	stream.write_all(&command)?;
+	stream.write_all(&uair::header())?;
+	stream.write_all(&uair::encode_frame(&command)?)?;
 	stream.shutdown(Shutdown::Write)?;
 
-	match args.command {
-		Command::Fetch(_) => {
-			let mut buf = String::new();
-			stream.read_to_string(&mut buf)?;
-
-			write!(io::stdout(), "{}", buf)?;
-		}
-		Command::Listen(_) => {
-			let mut reader = BufReader::new(stream);
-			let mut buf = Vec::new();
-
-			loop {
-				reader.read_until(b'\0', &mut buf)?;
-				if buf.is_empty() {
-					break;
-				}
-				write!(io::stdout(), "{}", str::from_utf8(&buf)?)?;
-				buf.clear();
+	let mut reader = BufReader::new(stream);
+	let mut replied = false;
+	while let Some(reply) = read_reply(&mut reader)? {
+		replied = true;
+		match reply {
+			Reply::Error(msg) => return Err(Error::Reply(msg)),
+			Reply::Ok => {}
+			Reply::Output(output) => write!(io::stdout(), "{}", output)?,
+			Reply::Status(status) => writeln!(io::stdout(), "{}", serde_json::to_string(&status)?)?,
+			Reply::Notification(notification) => {
+				writeln!(io::stdout(), "{}", serde_json::to_string(&notification)?)?
 			}
 		}
-		_ => {}
+	}
+
+	if !replied && !matches!(command, Command::Listen(_) | Command::Subscribe(_)) {
+		return Err(Error::NoReply);
 	}
 
 	Ok(())
 }
 
+fn read_reply(reader: &mut impl Read) -> Result<Option<Reply>, Error> {
+	let mut len = [0; 4];
+	match reader.read_exact(&mut len) {
+		Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+		res => res?,
+	}
+	let len = u32::from_be_bytes(len) as usize;
+	if len > uair::MAX_FRAME_LEN {
+		return Err(io::Error::new(ErrorKind::InvalidData, "frame is too large").into());
+	}
+	let mut buf = vec![0; len];
+	reader.read_exact(&mut buf)?;
+	Ok(Some(bincode::deserialize(&buf)?))
+}
+
 fn unescape(input: &str) -> String {
 	let mut res = String::new();
 	let mut chars = input.char_indices();
@@ -101,22 +145,52 @@ struct Args {
 	socket: String,
 
 	#[argh(subcommand)]
-	command: Command,
+	command: Subcommand,
+}
+
+/// Subcommands of uairctl, which are sent to uair as a `Command` except for those handled by
+/// uairctl itself.
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Subcommand {
+	Pause(PauseArgs),
+	Resume(ResumeArgs),
+	Toggle(ToggleArgs),
+	Next(NextArgs),
+	Prev(PrevArgs),
+	Finish(FinishArgs),
+	Jump(JumpArgs),
+	Reload(ReloadArgs),
+	Fetch(FetchArgs),
+	Listen(ListenArgs),
+	Status(StatusArgs),
+	Subscribe(SubscribeArgs),
+	AddTime(AddTimeArgs),
+	SubtractTime(SubtractTimeArgs),
+	Restart(RestartArgs),
+	Stats(StatsArgs),
 }
 
 #[derive(thiserror::Error, Debug)]
 enum Error {
 	#[error("Serialization Error: {0}")]
 	Ser(#[from] bincode::Error),
+	#[error("JSON Error: {0}")]
+	Json(#[from] serde_json::Error),
 	#[error("Socket Connection Error: {0}")]
 	Io(#[from] io::Error),
-	#[error("UTF8 Error: {0}")]
-	Utf8(#[from] str::Utf8Error),
+	#[error("Error: {0}")]
+	Reply(String),
+	#[error("History Error: {0}")]
+	History(String),
+	#[error("Error: uair closed the connection without replying, make sure that uair and uairctl are of the same version")]
+	NoReply,
 }
 
 #[cfg(test)]
 mod tests {
-	use super::unescape;
+	use super::{read_reply, unescape};
+	use uair::{Reply, MAX_FRAME_LEN};
 
 	#[test]
 	fn unescape_test() {
@@ -129,4 +203,14 @@ mod tests {
 		assert_eq!(unescape(r"\nFoo"), "\nFoo");
 		assert_eq!(unescape(r"Foo\"), "Foo\\");
 	}
+
+	#[test]
+	fn read_reply_test() {
+		let frame = uair::encode_frame(&Reply::Ok).unwrap();
+		assert!(matches!(read_reply(&mut &frame[..]), Ok(Some(Reply::Ok))));
+		assert!(matches!(read_reply(&mut &[][..]), Ok(None)));
+
+		let len = (MAX_FRAME_LEN as u32 + 1).to_be_bytes();
+		assert!(read_reply(&mut &len[..]).is_err());
+	}
 }
@@ -0,0 +1,238 @@
+use crate::Error;
+use argh::FromArgs;
+use chrono::{Datelike, Local, NaiveDate};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::time::Duration;
+use uair::{get_history_path, HistoryEntry, Outcome};
+
+#[derive(FromArgs)]
+/// Summarize the recorded session history. Does not require uair to be running.
+#[argh(subcommand, name = "stats")]
+pub struct StatsArgs {
+	/// output the summary as a JSON object
+	#[argh(switch, short = 'j')]
+	json: bool,
+	/// specifies the history file
+	#[argh(option, short = 'f')]
+	file: Option<String>,
+}
+
+pub fn run(args: &StatsArgs) -> Result<(), Error> {
+	let path = args.file.clone().unwrap_or_else(get_history_path);
+	let data = fs::read_to_string(&path)
+		.map_err(|err| Error::History(format!("Could not read \"{}\": {}", path, err)))?;
+
+	let mut entries = Vec::new();
+	for (i, line) in data.lines().enumerate() {
+		if line.trim().is_empty() {
+			continue;
+		}
+		match serde_json::from_str::<HistoryEntry>(line) {
+			Ok(entry) => entries.push(entry),
+			Err(err) => {
+				_ = writeln!(
+					io::stderr(),
+					"Skipping line {} of \"{}\": {}",
+					i + 1,
+					path,
+					err
+				)
+			}
+		}
+	}
+
+	let stats = Stats::new(&entries, Local::now().date_naive());
+	let mut stdout = io::stdout();
+	if args.json {
+		writeln!(stdout, "{}", serde_json::to_string(&stats)?)?;
+	} else {
+		write!(stdout, "{}", stats)?;
+	}
+	Ok(())
+}
+
+#[derive(Serialize, Default)]
+struct Stats {
+	daily: BTreeMap<String, BTreeMap<String, Totals>>,
+	weekly: BTreeMap<String, BTreeMap<String, Totals>>,
+	sessions: BTreeMap<String, Totals>,
+	streak: Streak,
+}
+
+impl Stats {
+	fn new(entries: &[HistoryEntry], today: NaiveDate) -> Self {
+		let mut stats = Stats::default();
+		let mut focus_days = BTreeSet::new();
+		for entry in entries {
+			let date = entry.start.with_timezone(&Local).date_naive();
+			let week = date.iso_week();
+			stats
+				.daily
+				.entry(date.to_string())
+				.or_default()
+				.entry(entry.id.clone())
+				.or_default()
+				.add(entry);
+			stats
+				.weekly
+				.entry(format!("{}-W{:02}", week.year(), week.week()))
+				.or_default()
+				.entry(entry.id.clone())
+				.or_default()
+				.add(entry);
+			stats
+				.sessions
+				.entry(entry.id.clone())
+				.or_default()
+				.add(entry);
+			if entry.outcome == Outcome::Completed {
+				focus_days.insert(date);
+			}
+		}
+		stats.streak = Streak::new(&focus_days, today);
+		stats
+	}
+}
+
+impl fmt::Display for Stats {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let width = self.sessions.keys().map(String::len).max().unwrap_or(0);
+		for (title, table) in [("Daily", &self.daily), ("Weekly", &self.weekly)] {
+			writeln!(f, "{}", title)?;
+			for (period, sessions) in table {
+				for (id, totals) in sessions {
+					writeln!(f, "  {:<10}  {:<width$}  {}", period, id, totals)?;
+				}
+			}
+			writeln!(f)?;
+		}
+		writeln!(f, "Sessions")?;
+		for (id, totals) in &self.sessions {
+			writeln!(f, "  {:<width$}  {}", id, totals)?;
+		}
+		writeln!(f)?;
+		writeln!(
+			f,
+			"Focus streak: {} days (longest: {} days)",
+			self.streak.current, self.streak.longest
+		)
+	}
+}
+
+#[derive(Serialize, Default)]
+struct Totals {
+	completed: u64,
+	skipped: u64,
+	interrupted: u64,
+	focused: u64,
+	completion_ratio: Option<f64>,
+}
+
+impl Totals {
+	fn add(&mut self, entry: &HistoryEntry) {
+		match entry.outcome {
+			Outcome::Completed => self.completed += 1,
+			Outcome::Skipped => self.skipped += 1,
+			Outcome::Interrupted => self.interrupted += 1,
+		}
+		self.focused += entry.focused;
+		let ended = self.completed + self.skipped;
+		self.completion_ratio = (ended != 0).then(|| self.completed as f64 / ended as f64);
+	}
+}
+
+impl fmt::Display for Totals {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} focused, {} completed, {} skipped, {} interrupted",
+			humantime::format_duration(Duration::from_secs(self.focused)),
+			self.completed,
+			self.skipped,
+			self.interrupted
+		)?;
+		if let Some(ratio) = self.completion_ratio {
+			write!(f, " ({:.0}% completed)", ratio * 100.0)?;
+		}
+		Ok(())
+	}
+}
+
+/// Runs of consecutive days with at least one completed session. The current streak is still
+/// alive if the last such day is today or yesterday.
+#[derive(Serialize, Default, Debug, PartialEq)]
+struct Streak {
+	current: u64,
+	longest: u64,
+}
+
+impl Streak {
+	fn new(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> Self {
+		let mut streak = Streak::default();
+		let mut run = 0;
+		let mut prev: Option<NaiveDate> = None;
+		for &day in days {
+			run = if prev.and_then(|p| p.succ_opt()) == Some(day) {
+				run + 1
+			} else {
+				1
+			};
+			streak.longest = streak.longest.max(run);
+			prev = Some(day);
+		}
+		if prev.is_some_and(|last| last == today || last.succ_opt() == Some(today)) {
+			streak.current = run;
+		}
+		streak
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(id: &str, outcome: Outcome, start: &str) -> HistoryEntry {
+		let start = chrono::DateTime::parse_from_rfc3339(start).unwrap();
+		HistoryEntry {
+			id: id.into(),
+			name: id.into(),
+			outcome,
+			planned: 1500,
+			focused: 1500,
+			pauses: 0,
+			start,
+			end: start,
+		}
+	}
+
+	#[test]
+	fn summarize_history() {
+		let entries = [
+			entry("work", Outcome::Completed, "2024-01-01T12:00:00Z"),
+			entry("work", Outcome::Completed, "2024-01-02T12:00:00Z"),
+			entry("work", Outcome::Completed, "2024-01-03T12:00:00Z"),
+			entry("work", Outcome::Skipped, "2024-01-05T12:00:00Z"),
+			entry("work", Outcome::Completed, "2024-01-06T12:00:00Z"),
+			entry("rest", Outcome::Interrupted, "2024-01-06T12:00:00Z"),
+		];
+		let today = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+		let stats = Stats::new(&entries, today);
+
+		let work = &stats.sessions["work"];
+		assert_eq!((work.completed, work.skipped, work.focused), (4, 1, 7500));
+		assert_eq!(work.completion_ratio, Some(0.8));
+		assert_eq!(stats.sessions["rest"].completion_ratio, None);
+		assert_eq!(stats.weekly["2024-W01"]["work"].completed, 4);
+		assert_eq!(
+			stats.streak,
+			Streak {
+				current: 1,
+				longest: 3
+			}
+		);
+	}
+}
@@ -1,9 +1,22 @@
 use argh::FromArgs;
+use chrono::{DateTime, FixedOffset};
+use serde::de::Error as _;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Magic bytes which precede the protocol version at the start of every connection.
+pub const PROTOCOL_MAGIC: [u8; 4] = *b"UAIR";
+/// Version of the socket protocol. Must be bumped whenever `Command` or `Reply` change in a way
+/// which breaks compatibility with clients of the last release, at most once per release.
+pub const PROTOCOL_VERSION: u16 = 1;
+pub const HEADER_LEN: usize = 6;
+pub const MAX_FRAME_LEN: usize = 1 << 20;
 
 #[derive(FromArgs, Serialize, Deserialize)]
 #[argh(subcommand)]
+#[serde(rename_all = "kebab-case")]
 pub enum Command {
 	Pause(PauseArgs),
 	Resume(ResumeArgs),
@@ -15,6 +28,11 @@ pub enum Command {
 	Reload(ReloadArgs),
 	Fetch(FetchArgs),
 	Listen(ListenArgs),
+	Status(StatusArgs),
+	Subscribe(SubscribeArgs),
+	AddTime(AddTimeArgs),
+	SubtractTime(SubtractTimeArgs),
+	Restart(RestartArgs),
 }
 
 #[derive(FromArgs, Serialize, Deserialize)]
@@ -68,6 +86,10 @@ pub struct FetchArgs {
 	/// output format
 	#[argh(positional)]
 	pub format: String,
+	/// markup used for colors and styles: ansi, pango, polybar, lemonbar, tmux or plain
+	#[argh(option, short = 'm')]
+	#[serde(default)]
+	pub markup: Option<Markup>,
 }
 
 #[derive(FromArgs, Serialize, Deserialize)]
@@ -76,10 +98,215 @@ pub struct FetchArgs {
 pub struct ListenArgs {
 	/// override to apply
 	#[argh(option, short = 'o', long = "override")]
+	#[serde(rename = "override")]
 	pub overrid: Option<String>,
 	/// output time and exit listening instance immediately
 	#[argh(switch, short = 'e')]
+	#[serde(default)]
 	pub exit: bool,
+	/// markup used for colors and styles: ansi, pango, polybar, lemonbar, tmux or plain
+	#[argh(option, short = 'm')]
+	#[serde(default)]
+	pub markup: Option<Markup>,
+	/// output a JSON object for waybar custom modules on every line
+	#[argh(switch, short = 'j')]
+	#[serde(default)]
+	pub json: bool,
+}
+
+#[derive(FromArgs, Serialize, Deserialize)]
+/// Output the state of the timer as a JSON object.
+#[argh(subcommand, name = "status")]
+pub struct StatusArgs {}
+
+#[derive(FromArgs, Serialize, Deserialize)]
+/// Output a JSON object for every change in the state of the timer.
+#[argh(subcommand, name = "subscribe")]
+pub struct SubscribeArgs {}
+
+#[derive(FromArgs, Serialize, Deserialize)]
+/// Add time to the current session.
+#[argh(subcommand, name = "add-time")]
+pub struct AddTimeArgs {
+	/// duration to add, e.g. "5m"
+	#[argh(positional, from_str_fn(parse_duration))]
+	#[serde(with = "humantime_serde")]
+	pub duration: Duration,
+}
+
+#[derive(FromArgs, Serialize, Deserialize)]
+/// Subtract time from the current session, finishing it if no time remains.
+#[argh(subcommand, name = "subtract-time")]
+pub struct SubtractTimeArgs {
+	/// duration to subtract, e.g. "5m"
+	#[argh(positional, from_str_fn(parse_duration))]
+	#[serde(with = "humantime_serde")]
+	pub duration: Duration,
+}
+
+#[derive(FromArgs, Serialize, Deserialize)]
+/// Restart the current session from its full duration.
+#[argh(subcommand, name = "restart")]
+pub struct RestartArgs {
+	/// keep the state (paused/resumed) of the timer instead of following the session's autostart
+	#[argh(switch, short = 'k')]
+	#[serde(default)]
+	pub keep_state: bool,
+}
+
+/// Markup language in which colors and styles of format specifiers are output.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Markup {
+	Ansi,
+	Pango,
+	Polybar,
+	Lemonbar,
+	Tmux,
+	Plain,
+}
+
+impl FromStr for Markup {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"ansi" => Ok(Markup::Ansi),
+			"pango" => Ok(Markup::Pango),
+			"polybar" => Ok(Markup::Polybar),
+			"lemonbar" => Ok(Markup::Lemonbar),
+			"tmux" => Ok(Markup::Tmux),
+			"plain" => Ok(Markup::Plain),
+			_ => Err(format!(
+				"unknown markup '{}', expected one of: ansi, pango, polybar, lemonbar, tmux, plain",
+				s
+			)),
+		}
+	}
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+	humantime::parse_duration(value).map_err(|err| err.to_string())
+}
+
+impl Command {
+	/// Parses a command from a JSON object whose "cmd" key holds the name of the subcommand and
+	/// whose other keys hold its arguments, e.g. `{"cmd":"jump","id":"work"}`.
+	pub fn from_json(line: &[u8]) -> serde_json::Result<Command> {
+		let mut args: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(line)?;
+		let cmd = match args.remove("cmd") {
+			Some(serde_json::Value::String(cmd)) => cmd,
+			_ => return Err(serde_json::Error::custom("missing field `cmd`")),
+		};
+		let mut command = serde_json::Map::new();
+		command.insert(cmd, args.into());
+		serde_json::from_value(command.into())
+	}
+}
+
+// `Error` must remain the first variant, so that clients speaking any protocol version are able
+// to decode the reply sent when their version is rejected.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reply {
+	Error(String),
+	Ok,
+	Output(String),
+	Status(Status),
+	Notification(Notification),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Status {
+	pub id: String,
+	pub name: String,
+	pub index: usize,
+	pub iteration: u64,
+	pub total_iterations: Option<u64>,
+	pub state: TimerState,
+	pub remaining: u64,
+	pub total: u64,
+	pub autostart: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimerState {
+	Paused,
+	Resumed,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Notification {
+	pub kind: NotificationKind,
+	pub id: String,
+	pub name: String,
+	pub index: usize,
+	pub iteration: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+	Started,
+	Paused,
+	Resumed,
+	Finished,
+	Skipped,
+	Jumped,
+	Reloaded,
+	Restarted,
+	TimerFinished,
+}
+
+/// A record of a session in the history file. Durations are in seconds.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+	pub id: String,
+	pub name: String,
+	pub outcome: Outcome,
+	pub planned: u64,
+	pub focused: u64,
+	pub pauses: u32,
+	pub start: DateTime<FixedOffset>,
+	pub end: DateTime<FixedOffset>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+	Completed,
+	Skipped,
+	Interrupted,
+}
+
+pub fn header() -> [u8; HEADER_LEN] {
+	let mut header = [0; HEADER_LEN];
+	header[..4].copy_from_slice(&PROTOCOL_MAGIC);
+	header[4..].copy_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+	header
+}
+
+pub fn check_header(header: &[u8; HEADER_LEN]) -> Result<(), String> {
+	if header[..4] != PROTOCOL_MAGIC {
+		return Err("invalid protocol header".into());
+	}
+	let version = u16::from_be_bytes([header[4], header[5]]);
+	if version != PROTOCOL_VERSION {
+		return Err(format!(
+			"client uses protocol version {}, but uair uses protocol version {}",
+			version, PROTOCOL_VERSION
+		));
+	}
+	Ok(())
+}
+
+pub fn encode_frame<T: Serialize>(value: &T) -> bincode::Result<Vec<u8>> {
+	let len = bincode::serialized_size(value)?;
+	let mut frame = Vec::with_capacity(4 + len as usize);
+	frame.extend_from_slice(&(len as u32).to_be_bytes());
+	bincode::serialize_into(&mut frame, value)?;
+	Ok(frame)
 }
 
 pub fn get_socket_path() -> String {
@@ -91,3 +318,77 @@ pub fn get_socket_path() -> String {
 		"/tmp/uair.sock".into()
 	}
 }
+
+pub fn get_state_path() -> String {
+	get_state_dir() + "/state"
+}
+
+pub fn get_history_path() -> String {
+	get_state_dir() + "/history"
+}
+
+fn get_state_dir() -> String {
+	if let Ok(xdg_state_home) = env::var("XDG_STATE_HOME") {
+		xdg_state_home + "/uair"
+	} else if let Ok(home) = env::var("HOME") {
+		home + "/.local/state/uair"
+	} else {
+		"/tmp/uair".into()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn check_protocol_header() {
+		assert_eq!(check_header(&header()), Ok(()));
+		assert_eq!(
+			check_header(b"UAIR\xff\xff"),
+			Err(format!(
+				"client uses protocol version 65535, but uair uses protocol version {}",
+				PROTOCOL_VERSION
+			))
+		);
+		assert_eq!(
+			check_header(b"\0\0\0\0\0\0"),
+			Err("invalid protocol header".into())
+		);
+	}
+
+	#[test]
+	fn parse_json_command() {
+		assert!(matches!(
+			Command::from_json(br#"{"cmd":"toggle"}"#),
+			Ok(Command::Toggle(_))
+		));
+		assert!(matches!(
+			Command::from_json(br#"{"cmd":"jump","id":"work"}"#),
+			Ok(Command::Jump(JumpArgs { id })) if id == "work"
+		));
+		assert!(matches!(
+			Command::from_json(br#"{"cmd":"listen"}"#),
+			Ok(Command::Listen(ListenArgs {
+				overrid: None,
+				exit: false,
+				markup: None,
+				json: false
+			}))
+		));
+		assert!(matches!(
+			Command::from_json(br#"{"cmd":"listen","override":"bar","exit":true}"#),
+			Ok(Command::Listen(ListenArgs { overrid: Some(o), exit: true, .. })) if o == "bar"
+		));
+		assert!(matches!(
+			Command::from_json(br#"{"cmd":"listen","markup":"pango"}"#),
+			Ok(Command::Listen(ListenArgs {
+				markup: Some(Markup::Pango),
+				..
+			}))
+		));
+		assert!(Command::from_json(br#"{"cmd":"jump"}"#).is_err());
+		assert!(Command::from_json(br#"{"cmd":"unknown"}"#).is_err());
+		assert!(Command::from_json(br#"{"id":"work"}"#).is_err());
+	}
+}